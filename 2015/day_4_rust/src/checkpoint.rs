@@ -0,0 +1,155 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Entry {
+    // Every nonce below this one was hashed without a match.
+    Searched(u64),
+    // The lowest matching nonce.
+    Found(u64),
+}
+
+impl Entry {
+    // Nonce a search has to resume from, or `None` when it is done.
+    pub fn resume_from(&self) -> Option<u64> {
+        match self {
+            Entry::Searched(nonce) => Some(*nonce),
+            Entry::Found(_) => None,
+        }
+    }
+}
+
+// Search state per key and difficulty, stored one per line as
+// `key<TAB>difficulty<TAB>searched|found<TAB>nonce`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Checkpoint {
+    entries: HashMap<(String, String), Entry>,
+}
+
+impl Checkpoint {
+    // Lines that do not parse are skipped, a damaged file only loses the
+    // progress recorded on them.
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                let [key, difficulty, state, nonce] = fields[..] else {
+                    return None;
+                };
+                let nonce: u64 = nonce.parse().ok()?;
+                let entry = match state {
+                    "searched" => Entry::Searched(nonce),
+                    "found" => Entry::Found(nonce),
+                    _ => return None,
+                };
+                Some(((key.to_string(), difficulty.to_string()), entry))
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Checkpoint::parse(&text)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Checkpoint::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<String> = self
+            .entries
+            .iter()
+            .map(|((key, difficulty), entry)| {
+                let (state, nonce) = match entry {
+                    Entry::Searched(nonce) => ("searched", nonce),
+                    Entry::Found(nonce) => ("found", nonce),
+                };
+                format!("{}\t{}\t{}\t{}\n", key, difficulty, state, nonce)
+            })
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+
+    // Written next to the target first and renamed over it, so killing the
+    // process mid write leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, self.to_text())?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn get(&self, key: &str, difficulty: &str) -> Option<Entry> {
+        self.entries
+            .get(&(key.to_string(), difficulty.to_string()))
+            .copied()
+    }
+
+    // Keeps whichever entry records more work, a found nonce beats any
+    // searched range.
+    pub fn update(&mut self, key: &str, difficulty: &str, entry: Entry) {
+        let slot = self
+            .entries
+            .entry((key.to_string(), difficulty.to_string()))
+            .or_insert(entry);
+
+        *slot = match (*slot, entry) {
+            (Entry::Found(nonce), _) | (_, Entry::Found(nonce)) => Entry::Found(nonce),
+            (Entry::Searched(a), Entry::Searched(b)) => Entry::Searched(a.max(b)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let mut checkpoint = Checkpoint::default();
+        checkpoint.update("abcdef", "zeros:6", Entry::Searched(4096));
+        checkpoint.update("abcdef", "zeros:6", Entry::Searched(1024));
+        checkpoint.update("abcdef", "zeros:5", Entry::Found(609043));
+        checkpoint.update("abcdef", "zeros:5", Entry::Searched(700000));
+
+        assert_eq!(
+            checkpoint.get("abcdef", "zeros:6"),
+            Some(Entry::Searched(4096))
+        );
+        assert_eq!(
+            checkpoint.get("abcdef", "zeros:5"),
+            Some(Entry::Found(609043))
+        );
+        assert_eq!(checkpoint.get("pqrstuv", "zeros:5"), None);
+        assert_eq!(
+            checkpoint.to_text(),
+            "abcdef\tzeros:5\tfound\t609043\nabcdef\tzeros:6\tsearched\t4096\n"
+        );
+        assert_eq!(Checkpoint::parse(&checkpoint.to_text()), checkpoint);
+
+        let damaged = Checkpoint::parse(
+            "abcdef\tzeros:6\tsearched\nabc\tzeros:5\tfound\t12\nabc\tbits:3\tlost\t1\n",
+        );
+        assert_eq!(damaged.get("abc", "zeros:5"), Some(Entry::Found(12)));
+        assert_eq!(damaged.get("abcdef", "zeros:6"), None);
+        assert_eq!(damaged.get("abc", "bits:3"), None);
+    }
+
+    #[test]
+    fn test_checkpoint_file() {
+        let path =
+            std::env::temp_dir().join(format!("day_4_checkpoint_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(Checkpoint::load(&path).unwrap(), Checkpoint::default());
+
+        let mut checkpoint = Checkpoint::default();
+        checkpoint.update("key", "prefix:abc", Entry::Searched(8192));
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
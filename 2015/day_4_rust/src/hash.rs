@@ -0,0 +1,170 @@
+use sha1::Digest as _;
+
+// A hash split into absorbing the key once and finishing a copy of that
+// state with every nonce, so the key is not hashed again per nonce.
+pub trait HashAlgorithm: Sync {
+    type Context: Clone;
+    type Digest: AsRef<[u8]>;
+
+    fn context(&self, key: &[u8]) -> Self::Context;
+
+    fn finish(&self, context: Self::Context, data: &[u8]) -> Self::Digest;
+
+    fn hash(&self, key: &[u8], data: &[u8]) -> Self::Digest {
+        self.finish(self.context(key), data)
+    }
+}
+
+pub struct Md5;
+
+impl HashAlgorithm for Md5 {
+    type Context = md5::Context;
+    type Digest = [u8; 16];
+
+    fn context(&self, key: &[u8]) -> md5::Context {
+        let mut context = md5::Context::new();
+        context.consume(key);
+        context
+    }
+
+    fn finish(&self, mut context: md5::Context, data: &[u8]) -> [u8; 16] {
+        context.consume(data);
+        context.compute().0
+    }
+}
+
+pub struct Sha1;
+
+impl HashAlgorithm for Sha1 {
+    type Context = sha1::Sha1;
+    type Digest = [u8; 20];
+
+    fn context(&self, key: &[u8]) -> sha1::Sha1 {
+        sha1::Sha1::new_with_prefix(key)
+    }
+
+    fn finish(&self, mut context: sha1::Sha1, data: &[u8]) -> [u8; 20] {
+        context.update(data);
+        context.finalize().into()
+    }
+}
+
+pub struct Sha256;
+
+impl HashAlgorithm for Sha256 {
+    type Context = sha2::Sha256;
+    type Digest = [u8; 32];
+
+    fn context(&self, key: &[u8]) -> sha2::Sha256 {
+        sha2::Sha256::new_with_prefix(key)
+    }
+
+    fn finish(&self, mut context: sha2::Sha256, data: &[u8]) -> [u8; 32] {
+        context.update(data);
+        context.finalize().into()
+    }
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+pub fn hex_char(digest: &[u8], index: usize) -> Option<char> {
+    let byte = digest.get(index / 2)?;
+    let nibble = if index.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0x0f
+    };
+    Some(HEX_DIGITS[nibble as usize] as char)
+}
+
+pub fn to_hex(digest: &[u8]) -> String {
+    (0..digest.len() * 2)
+        .filter_map(|index| hex_char(digest, index))
+        .collect()
+}
+
+// Key stretching as in 2016 day 14: the digest is hashed again as lowercase
+// hex `rounds` more times.
+pub struct Stretched<H: HashAlgorithm> {
+    pub inner: H,
+    pub rounds: usize,
+}
+
+impl<H: HashAlgorithm> HashAlgorithm for Stretched<H> {
+    type Context = H::Context;
+    type Digest = H::Digest;
+
+    fn context(&self, key: &[u8]) -> H::Context {
+        self.inner.context(key)
+    }
+
+    fn finish(&self, context: H::Context, data: &[u8]) -> H::Digest {
+        let mut digest = self.inner.finish(context, data);
+        let mut hex = [0_u8; 128];
+
+        for _ in 0..self.rounds {
+            let bytes = digest.as_ref();
+            for (index, byte) in bytes.iter().enumerate() {
+                hex[index * 2] = HEX_DIGITS[(byte >> 4) as usize];
+                hex[index * 2 + 1] = HEX_DIGITS[(byte & 0x0f) as usize];
+            }
+            let length = bytes.len() * 2;
+            digest = self.inner.hash(&hex[..length], &[]);
+        }
+
+        digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algorithms() {
+        assert_eq!(
+            to_hex(&Md5.hash(b"ab", b"c")),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            to_hex(&Sha1.hash(b"ab", b"c")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            to_hex(&Sha256.hash(b"ab", b"c")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_stretched() {
+        let stretched = Stretched {
+            inner: Md5,
+            rounds: 2016,
+        };
+
+        assert_eq!(
+            to_hex(&stretched.hash(b"abc", b"0")),
+            "a107ff634856bb300138cac6568c0f24"
+        );
+        assert_eq!(
+            Stretched {
+                inner: Md5,
+                rounds: 0,
+            }
+            .hash(b"abc", b"0"),
+            Md5.hash(b"abc", b"0")
+        );
+    }
+
+    #[test]
+    fn test_hex_char() {
+        let digest = [0x1f, 0xa0];
+
+        assert_eq!(hex_char(&digest, 0), Some('1'));
+        assert_eq!(hex_char(&digest, 1), Some('f'));
+        assert_eq!(hex_char(&digest, 2), Some('a'));
+        assert_eq!(hex_char(&digest, 4), None);
+        assert_eq!(to_hex(&digest), "1fa0");
+    }
+}
@@ -0,0 +1,5 @@
+pub mod checkpoint;
+pub mod hash;
+pub mod miner;
+pub mod predicate;
+pub mod progress;
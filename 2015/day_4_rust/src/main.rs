@@ -1,4 +1,22 @@
-use std::{fs, io::Error};
+use std::{
+    env, fs,
+    io::Error,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use day_4_rust::{
+    checkpoint::{Checkpoint, Entry},
+    hash::Md5,
+    miner::{mine_from, MineError, Progress},
+    predicate::Predicate,
+    progress::{estimate, format_progress},
+};
+
+const CHECKPOINT_FILE: &str = "checkpoint.txt";
+const REPORT_EVERY: Duration = Duration::from_secs(1);
+const USAGE: &str = "Usage: day_4_rust [leading zero count]...";
 
 fn generate_hash_for(key: &str, value: u64) -> String {
     let input = format!("{}{}", key, value);
@@ -6,32 +24,141 @@ fn generate_hash_for(key: &str, value: u64) -> String {
     format!("{:x}", digest)
 }
 
+// Entries worth keeping after `progress`: a match is only final once every
+// nonce below it has been searched.
+fn progress_entries(progress: &Progress) -> Vec<Entry> {
+    progress
+        .found
+        .iter()
+        .map(|found| match found {
+            Some(nonce) if *nonce < progress.searched => Entry::Found(*nonce),
+            _ => Entry::Searched(progress.searched),
+        })
+        .collect()
+}
+
+// Leading zero counts to search for, parts 1 and 2 by default.
+fn parse_zeros(args: &[String]) -> Option<Vec<usize>> {
+    if args.is_empty() {
+        return Some(vec![5, 6]);
+    }
+
+    args.iter()
+        .map(|arg| {
+            let count = arg.parse().ok();
+            if count.is_none() {
+                println!("Not a leading zero count: {}", arg);
+            }
+            count
+        })
+        .collect()
+}
+
 fn main() -> Result<(), Error> {
     let secret_key_read = fs::read_to_string("input.txt")?;
     let secret_key = secret_key_read.trim_end();
 
-    let mut i: u64 = 0;
-    let mut hash: String;
-    loop {
-        hash = generate_hash_for(&secret_key, i);
-        if hash.starts_with("00000") {
-            break;
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(zeros) = parse_zeros(&args) else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    let predicates: Vec<Predicate> = zeros
+        .iter()
+        .map(|count| Predicate::LeadingZeroNibbles(*count))
+        .collect();
+
+    let checkpoint_path = Path::new(CHECKPOINT_FILE);
+    let checkpoint = Checkpoint::load(checkpoint_path)?;
+
+    let resume: Vec<(Predicate, u64)> = predicates
+        .iter()
+        .filter_map(|predicate| {
+            match checkpoint.get(secret_key, &predicate.to_string()) {
+                Some(entry) => entry.resume_from(),
+                None => Some(0),
+            }
+            .map(|start| (predicate.clone(), start))
+        })
+        .collect();
+    let checkpoint = Mutex::new(checkpoint);
+
+    if let Some(start) = resume.iter().map(|(_, start)| *start).min() {
+        let pending: Vec<Predicate> = resume.into_iter().map(|(predicate, _)| predicate).collect();
+        if start > 0 {
+            println!("Resuming from nonce {}", start);
         }
-        i += 1;
-    }
 
-    println!("Part 1: lowest number is {} that generates that [{}] hash", i, hash );
-    
-    i = 0;
-    loop {
-        hash = generate_hash_for(&secret_key, i);
-        if hash.starts_with("000000") {
-            break;
+        let started = Instant::now();
+        let last_report = Mutex::new(started);
+        let save_progress = |progress: &Progress| {
+            let mut checkpoint = checkpoint.lock().unwrap();
+            for (predicate, entry) in pending.iter().zip(progress_entries(progress)) {
+                checkpoint.update(secret_key, &predicate.to_string(), entry);
+            }
+            checkpoint.save(checkpoint_path)
+        };
+
+        let found = match mine_from(&Md5, secret_key, &pending, start, |progress| {
+            let mut last_report = last_report.lock().unwrap();
+            if last_report.elapsed() < REPORT_EVERY {
+                return;
+            }
+            *last_report = Instant::now();
+
+            let expected_work = pending
+                .iter()
+                .zip(progress_entries(progress))
+                .filter(|(_, entry)| entry.resume_from().is_some())
+                .map(|(predicate, _)| predicate.expected_work())
+                .fold(0.0, f64::max);
+            let estimate = estimate(
+                progress.searched - start,
+                started.elapsed(),
+                progress.searched,
+                expected_work,
+            );
+            println!("{}", format_progress(progress.searched, &estimate));
+
+            if let Err(error) = save_progress(progress) {
+                println!("Could not save checkpoint: {}", error);
+            }
+        }) {
+            Ok(found) => found,
+            Err(MineError::Unsatisfiable { index, digest_len }) => {
+                println!(
+                    "No {}-byte MD5 digest can match {}",
+                    digest_len, pending[index]
+                );
+                return Ok(());
+            }
+        };
+
+        let mut checkpoint = checkpoint.lock().unwrap();
+        for (predicate, nonce) in pending.iter().zip(found) {
+            checkpoint.update(secret_key, &predicate.to_string(), Entry::Found(nonce));
         }
-        i += 1;
+        checkpoint.save(checkpoint_path)?;
     }
 
-    println!("Part 2: lowest number is {} that generates that [{}] hash", i, hash );
+    let checkpoint = checkpoint.into_inner().unwrap();
+    for (index, predicate) in predicates.iter().enumerate() {
+        let Some(Entry::Found(nonce)) = checkpoint.get(secret_key, &predicate.to_string()) else {
+            continue;
+        };
+        let label = if args.is_empty() {
+            format!("Part {}", index + 1)
+        } else {
+            predicate.to_string()
+        };
+
+        println!(
+            "{}: lowest number is {} that generates that [{}] hash",
+            label,
+            nonce,
+            generate_hash_for(secret_key, nonce)
+        );
+    }
 
     Ok(())
 }
@@ -40,6 +167,16 @@ fn main() -> Result<(), Error> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_zeros() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(parse_zeros(&[]), Some(vec![5, 6]));
+        assert_eq!(parse_zeros(&args(&["7", "3"])), Some(vec![7, 3]));
+        assert_eq!(parse_zeros(&args(&["7", "six"])), None);
+        assert_eq!(parse_zeros(&args(&["-1"])), None);
+    }
+
     #[test]
     fn test_generate_hash_for() {
         assert!(
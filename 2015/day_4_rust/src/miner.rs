@@ -0,0 +1,345 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::hash::HashAlgorithm;
+use crate::predicate::DigestPredicate;
+
+// Nonces handed to a thread at a time. Big enough to keep the shared
+// counter out of the hot loop, small enough that threads stop soon after
+// the last answer is found.
+const CHUNK_SIZE: u64 = 4096;
+
+// Writes the decimal digits of `value` to the end of `buffer` and returns
+// where they start, so no string is built per nonce.
+fn write_decimal(mut value: u64, buffer: &mut [u8; 20]) -> usize {
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            return start;
+        }
+    }
+}
+
+pub fn hash<H: HashAlgorithm>(hasher: &H, key: &str, nonce: u64) -> H::Digest {
+    hash_with(hasher, &hasher.context(key.as_bytes()), nonce)
+}
+
+fn hash_with<H: HashAlgorithm>(hasher: &H, key_context: &H::Context, nonce: u64) -> H::Digest {
+    let mut buffer = [0_u8; 20];
+    let start = write_decimal(nonce, &mut buffer);
+
+    hasher.finish(key_context.clone(), &buffer[start..])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    // Every nonce below this one has been hashed.
+    pub searched: u64,
+    // Lowest match so far per predicate. Only final once below `searched`.
+    pub found: Vec<Option<u64>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MineError {
+    // The predicate at `index` can't match any digest of the hash, so a
+    // search for it would never end.
+    Unsatisfiable { index: usize, digest_len: usize },
+}
+
+impl fmt::Display for MineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MineError::Unsatisfiable { index, digest_len } => write!(
+                f,
+                "predicate {} can't match any {}-byte digest",
+                index, digest_len
+            ),
+        }
+    }
+}
+
+// Chunks finish out of order, so finished chunks above the searched
+// frontier wait here until the gap below them is closed.
+struct Frontier {
+    searched: u64,
+    finished: BTreeSet<u64>,
+}
+
+struct Search<'a, H: HashAlgorithm, P, F> {
+    hasher: &'a H,
+    key_context: H::Context,
+    predicates: &'a [P],
+    on_progress: F,
+    next_chunk: AtomicU64,
+    found: Vec<AtomicU64>,
+    frontier: Mutex<Frontier>,
+}
+
+impl<H, P, F> Search<'_, H, P, F>
+where
+    H: HashAlgorithm,
+    P: DigestPredicate,
+    F: Fn(&Progress),
+{
+    fn search_chunks(&self) {
+        loop {
+            let start = self.next_chunk.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+
+            // Chunks are handed out in order, so once every answer lies below
+            // this chunk all lower chunks are taken and being searched.
+            let all_found = self
+                .found
+                .iter()
+                .all(|nonce| nonce.load(Ordering::Relaxed) < start);
+            if all_found {
+                return;
+            }
+
+            for nonce in start..start.saturating_add(CHUNK_SIZE) {
+                let digest = hash_with(self.hasher, &self.key_context, nonce);
+
+                for (predicate, lowest) in self.predicates.iter().zip(self.found.iter()) {
+                    if nonce < lowest.load(Ordering::Relaxed) && predicate.matches(digest.as_ref())
+                    {
+                        lowest.fetch_min(nonce, Ordering::Relaxed);
+                    }
+                }
+            }
+
+            self.finish_chunk(start);
+        }
+    }
+
+    // Reported while holding the frontier lock, so progress never goes
+    // backwards for the callback.
+    fn finish_chunk(&self, start: u64) {
+        let mut guard = self.frontier.lock().unwrap();
+        let frontier = &mut *guard;
+        frontier.finished.insert(start);
+
+        let before = frontier.searched;
+        while frontier.finished.remove(&frontier.searched) {
+            frontier.searched = frontier.searched.saturating_add(CHUNK_SIZE);
+        }
+
+        if frontier.searched > before {
+            (self.on_progress)(&Progress {
+                searched: frontier.searched,
+                found: self
+                    .found
+                    .iter()
+                    .map(|nonce| Some(nonce.load(Ordering::Relaxed)).filter(|n| *n != u64::MAX))
+                    .collect(),
+            });
+        }
+    }
+}
+
+// Lowest nonce for every predicate, searched in a single pass over the
+// nonces from `start` up, split across all cores. The result does not depend
+// on how the threads were scheduled. Nonces below `start` are assumed to be
+// already searched without a match. Fails up front if a predicate can never
+// match.
+pub fn mine_from<H, P, F>(
+    hasher: &H,
+    key: &str,
+    predicates: &[P],
+    start: u64,
+    on_progress: F,
+) -> Result<Vec<u64>, MineError>
+where
+    H: HashAlgorithm,
+    H::Context: Sync,
+    P: DigestPredicate,
+    F: Fn(&Progress) + Sync,
+{
+    let key_context = hasher.context(key.as_bytes());
+    let digest_len = hasher.finish(key_context.clone(), &[]).as_ref().len();
+    if let Some(index) = predicates
+        .iter()
+        .position(|predicate| !predicate.is_satisfiable(digest_len))
+    {
+        return Err(MineError::Unsatisfiable { index, digest_len });
+    }
+
+    let search = Search {
+        hasher,
+        key_context,
+        predicates,
+        on_progress,
+        next_chunk: AtomicU64::new(start),
+        found: predicates
+            .iter()
+            .map(|_| AtomicU64::new(u64::MAX))
+            .collect(),
+        frontier: Mutex::new(Frontier {
+            searched: start,
+            finished: BTreeSet::new(),
+        }),
+    };
+    let threads = thread::available_parallelism().map_or(1, |count| count.get());
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| search.search_chunks());
+        }
+    });
+
+    Ok(search
+        .found
+        .into_iter()
+        .map(AtomicU64::into_inner)
+        .collect())
+}
+
+pub fn mine_all<H, P>(hasher: &H, key: &str, predicates: &[P]) -> Result<Vec<u64>, MineError>
+where
+    H: HashAlgorithm,
+    H::Context: Sync,
+    P: DigestPredicate,
+{
+    mine_from(hasher, key, predicates, 0, |_| {})
+}
+
+pub fn mine<H, P>(hasher: &H, key: &str, predicate: P) -> Result<u64, MineError>
+where
+    H: HashAlgorithm,
+    H::Context: Sync,
+    P: DigestPredicate,
+{
+    Ok(mine_all(hasher, key, &[predicate])?[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{to_hex, Md5, Sha1, Sha256};
+    use crate::predicate::Predicate;
+
+    #[test]
+    fn test_hash() {
+        assert!(to_hex(&hash(&Md5, "abcdef", 609043)).starts_with("000001dbbfa"));
+        assert_eq!(
+            to_hex(&hash(&Md5, "abc", 0)),
+            format!("{:x}", md5::compute("abc0"))
+        );
+        assert_eq!(
+            to_hex(&hash(&Md5, "", u64::MAX)),
+            format!("{:x}", md5::compute(u64::MAX.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_mine() {
+        assert_eq!(
+            mine(&Md5, "abcdef", Predicate::LeadingZeroNibbles(5)),
+            Ok(609043)
+        );
+        assert_eq!(
+            mine(&Md5, "pqrstuv", Predicate::HexPrefix("00000".to_string())),
+            Ok(1048970)
+        );
+    }
+
+    #[test]
+    fn test_mine_unsatisfiable() {
+        assert_eq!(
+            mine(&Md5, "abcdef", Predicate::LeadingZeroNibbles(40)),
+            Err(MineError::Unsatisfiable {
+                index: 0,
+                digest_len: 16
+            })
+        );
+        assert_eq!(
+            mine_all(
+                &Sha1,
+                "abcdef",
+                &[
+                    Predicate::LeadingZeroBits(1),
+                    Predicate::LeadingZeroBits(161)
+                ]
+            ),
+            Err(MineError::Unsatisfiable {
+                index: 1,
+                digest_len: 20
+            })
+        );
+        assert_eq!(
+            mine(&Sha256, "abcdef", Predicate::HexPrefix("00x".to_string())),
+            Err(MineError::Unsatisfiable {
+                index: 0,
+                digest_len: 32
+            })
+        );
+    }
+
+    #[test]
+    fn test_mine_all_is_lowest() {
+        let predicates = [
+            Predicate::LeadingZeroNibbles(3),
+            Predicate::LeadingZeroBits(13),
+            Predicate::HexPrefix("abc".to_string()),
+        ];
+        let found = mine_all(&Sha256, "abcdef", &predicates).unwrap();
+
+        for (predicate, nonce) in predicates.iter().zip(found.iter()) {
+            let lowest = (0..)
+                .find(|nonce| predicate.matches(&hash(&Sha256, "abcdef", *nonce)))
+                .unwrap();
+            assert_eq!(*nonce, lowest);
+        }
+    }
+
+    #[test]
+    fn test_mine_from() {
+        let progress: Mutex<Vec<Progress>> = Mutex::new(Vec::new());
+        let found = mine_from(
+            &Md5,
+            "abcdef",
+            &[Predicate::LeadingZeroNibbles(4)],
+            CHUNK_SIZE * 3,
+            |update| progress.lock().unwrap().push(update.clone()),
+        )
+        .unwrap();
+        let lowest = (CHUNK_SIZE * 3..)
+            .find(|nonce| Predicate::LeadingZeroNibbles(4).matches(&hash(&Md5, "abcdef", *nonce)))
+            .unwrap();
+        assert_eq!(found, vec![lowest]);
+
+        let progress = progress.into_inner().unwrap();
+        assert_eq!(progress[0].searched, CHUNK_SIZE * 4);
+        assert!(progress
+            .windows(2)
+            .all(|pair| pair[0].searched < pair[1].searched));
+        let last = progress.last().unwrap();
+        assert!(last.searched > lowest);
+        assert_eq!(last.found, vec![Some(lowest)]);
+    }
+
+    #[test]
+    fn test_mine_with_closure() {
+        // 2016 day 5 style: five zeros and a valid position in the sixth
+        // hex character.
+        let predicate = |digest: &[u8]| {
+            Predicate::LeadingZeroNibbles(5).matches(digest)
+                && crate::hash::hex_char(digest, 5).is_some_and(|c| c < '8')
+        };
+        let nonce = mine(&Md5, "abc", predicate).unwrap();
+
+        assert_eq!(nonce, 3231929);
+        assert_eq!(
+            crate::hash::hex_char(&hash(&Md5, "abc", nonce), 5),
+            Some('1')
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+
+use crate::hash::hex_char;
+
+pub trait DigestPredicate: Sync {
+    fn matches(&self, digest: &[u8]) -> bool;
+
+    // Whether some digest of `digest_len` bytes can match at all. Closures
+    // can't be inspected, so they are assumed to be satisfiable.
+    fn is_satisfiable(&self, _digest_len: usize) -> bool {
+        true
+    }
+}
+
+impl<F: Fn(&[u8]) -> bool + Sync> DigestPredicate for F {
+    fn matches(&self, digest: &[u8]) -> bool {
+        self(digest)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    LeadingZeroNibbles(usize),
+    LeadingZeroBits(usize),
+    // Compared case insensitively against the hex form of the digest.
+    HexPrefix(String),
+}
+
+impl Predicate {
+    // Hashes needed on average for one match, assuming uniform digests.
+    pub fn expected_work(&self) -> f64 {
+        match self {
+            Predicate::LeadingZeroNibbles(count) => 16_f64.powi(*count as i32),
+            Predicate::LeadingZeroBits(count) => 2_f64.powi(*count as i32),
+            Predicate::HexPrefix(prefix) => 16_f64.powi(prefix.len() as i32),
+        }
+    }
+}
+
+// Short form used to tell difficulties apart, e.g. in checkpoint files.
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::LeadingZeroNibbles(count) => write!(f, "zeros:{}", count),
+            Predicate::LeadingZeroBits(count) => write!(f, "bits:{}", count),
+            Predicate::HexPrefix(prefix) => write!(f, "prefix:{}", prefix.to_ascii_lowercase()),
+        }
+    }
+}
+
+impl DigestPredicate for Predicate {
+    fn matches(&self, digest: &[u8]) -> bool {
+        match self {
+            Predicate::LeadingZeroNibbles(count) => {
+                Predicate::LeadingZeroBits(count * 4).matches(digest)
+            }
+            Predicate::LeadingZeroBits(count) => {
+                let (bytes, bits) = (count / 8, count % 8);
+                let Some(whole) = digest.get(..bytes) else {
+                    return false;
+                };
+
+                whole.iter().all(|byte| *byte == 0)
+                    && (bits == 0
+                        || digest
+                            .get(bytes)
+                            .is_some_and(|byte| byte.leading_zeros() as usize >= bits))
+            }
+            Predicate::HexPrefix(prefix) => prefix
+                .chars()
+                .enumerate()
+                .all(|(index, c)| hex_char(digest, index) == Some(c.to_ascii_lowercase())),
+        }
+    }
+
+    fn is_satisfiable(&self, digest_len: usize) -> bool {
+        match self {
+            Predicate::LeadingZeroNibbles(count) => *count <= digest_len * 2,
+            Predicate::LeadingZeroBits(count) => *count <= digest_len * 8,
+            Predicate::HexPrefix(prefix) => {
+                prefix.len() <= digest_len * 2 && prefix.chars().all(|c| c.is_ascii_hexdigit())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leading_zeros() {
+        let mut digest = [0xff_u8; 16];
+        assert!(Predicate::LeadingZeroNibbles(0).matches(&digest));
+        assert!(!Predicate::LeadingZeroNibbles(1).matches(&digest));
+
+        digest[0] = 0;
+        digest[1] = 0;
+        digest[2] = 0x0f;
+        assert!(Predicate::LeadingZeroNibbles(5).matches(&digest));
+        assert!(!Predicate::LeadingZeroNibbles(6).matches(&digest));
+        assert!(Predicate::LeadingZeroBits(20).matches(&digest));
+        assert!(!Predicate::LeadingZeroBits(21).matches(&digest));
+
+        digest[2] = 0x01;
+        assert!(Predicate::LeadingZeroBits(23).matches(&digest));
+        assert!(!Predicate::LeadingZeroBits(24).matches(&digest));
+        assert!(!Predicate::LeadingZeroBits(129).matches(&[0; 16]));
+        assert!(Predicate::LeadingZeroBits(128).matches(&[0; 16]));
+    }
+
+    #[test]
+    fn test_hex_prefix() {
+        let digest = [0x00, 0x00, 0x01, 0xdb, 0xbf];
+
+        assert!(Predicate::HexPrefix("000001dbbf".to_string()).matches(&digest));
+        assert!(Predicate::HexPrefix("000001DB".to_string()).matches(&digest));
+        assert!(Predicate::HexPrefix(String::new()).matches(&digest));
+        assert!(!Predicate::HexPrefix("000002".to_string()).matches(&digest));
+        assert!(!Predicate::HexPrefix("000001dbbf0".to_string()).matches(&digest));
+    }
+
+    #[test]
+    fn test_is_satisfiable() {
+        assert!(Predicate::LeadingZeroNibbles(32).is_satisfiable(16));
+        assert!(!Predicate::LeadingZeroNibbles(33).is_satisfiable(16));
+        assert!(Predicate::LeadingZeroBits(128).is_satisfiable(16));
+        assert!(!Predicate::LeadingZeroBits(129).is_satisfiable(16));
+        assert!(Predicate::HexPrefix("00AbF".to_string()).is_satisfiable(16));
+        assert!(!Predicate::HexPrefix("00g".to_string()).is_satisfiable(16));
+        assert!(!Predicate::HexPrefix("0".repeat(41)).is_satisfiable(20));
+        assert!((|_: &[u8]| false).is_satisfiable(16));
+    }
+
+    #[test]
+    fn test_display_and_expected_work() {
+        assert_eq!(Predicate::LeadingZeroNibbles(5).to_string(), "zeros:5");
+        assert_eq!(Predicate::LeadingZeroBits(20).to_string(), "bits:20");
+        assert_eq!(
+            Predicate::HexPrefix("0Ab".to_string()).to_string(),
+            "prefix:0ab"
+        );
+
+        assert_eq!(Predicate::LeadingZeroNibbles(5).expected_work(), 1048576.0);
+        assert_eq!(Predicate::LeadingZeroBits(20).expected_work(), 1048576.0);
+        assert_eq!(
+            Predicate::HexPrefix("abc".to_string()).expected_work(),
+            4096.0
+        );
+    }
+}
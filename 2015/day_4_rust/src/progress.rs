@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+// Speed and remaining time of a search. The estimate is the expected number
+// of hashes for the hardest predicate still without a match, so it is only
+// a guide and runs out when the search gets unlucky.
+#[derive(Debug, PartialEq)]
+pub struct Estimate {
+    pub hashes_per_second: f64,
+    pub remaining: Option<Duration>,
+}
+
+pub fn estimate(hashed: u64, elapsed: Duration, searched: u64, expected_work: f64) -> Estimate {
+    let seconds = elapsed.as_secs_f64();
+    let hashes_per_second = if seconds > 0.0 {
+        hashed as f64 / seconds
+    } else {
+        0.0
+    };
+
+    let left = expected_work - searched as f64;
+    let remaining = (hashes_per_second > 0.0 && left > 0.0)
+        .then(|| Duration::from_secs_f64(left / hashes_per_second));
+
+    Estimate {
+        hashes_per_second,
+        remaining,
+    }
+}
+
+pub fn format_progress(searched: u64, estimate: &Estimate) -> String {
+    let eta = match estimate.remaining {
+        Some(remaining) => format!("ETA {}s", remaining.as_secs()),
+        None => "ETA unknown, past expected work".to_string(),
+    };
+
+    format!(
+        "searched {} nonces, {:.0} H/s, {}",
+        searched, estimate.hashes_per_second, eta
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let progress = estimate(500_000, Duration::from_secs(2), 1_000_000, 16_f64.powi(6));
+
+        assert_eq!(progress.hashes_per_second, 250_000.0);
+        assert_eq!(
+            progress.remaining,
+            Some(Duration::from_secs_f64(15_777_216.0 / 250_000.0))
+        );
+        assert_eq!(
+            format_progress(1_000_000, &progress),
+            "searched 1000000 nonces, 250000 H/s, ETA 63s"
+        );
+
+        let overdue = estimate(10, Duration::from_secs(1), 2_000_000, 16_f64.powi(5));
+        assert_eq!(overdue.remaining, None);
+        assert_eq!(
+            format_progress(2_000_000, &overdue),
+            "searched 2000000 nonces, 10 H/s, ETA unknown, past expected work"
+        );
+        assert_eq!(estimate(0, Duration::ZERO, 0, 16.0).hashes_per_second, 0.0);
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    io::{self, BufRead, Write},
+    thread,
+};
+
+use crate::nice_string::StringType;
+use crate::rule::{check_all, RuleSet};
+
+// Lines read before classifying them together. Keeps memory bounded for
+// huge inputs while giving every thread enough work per chunk.
+const CHUNK_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counts {
+    pub lines: usize,
+    // Nice strings per rule set, in the order the sets were given.
+    pub nice: Vec<usize>,
+}
+
+fn classify_chunk(rule_sets: &[RuleSet], lines: &[String], threads: usize) -> Vec<Vec<StringType>> {
+    if threads <= 1 || lines.len() < 2 {
+        return lines
+            .iter()
+            .map(|line| check_all(rule_sets, line))
+            .collect();
+    }
+
+    let part_size = lines.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let parts: Vec<_> = lines
+            .chunks(part_size)
+            .map(|part| {
+                scope.spawn(move || {
+                    part.iter()
+                        .map(|line| check_all(rule_sets, line))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        parts
+            .into_iter()
+            .flat_map(|part| part.join().unwrap())
+            .collect()
+    })
+}
+
+// Reads `reader` line by line and classifies each line under every rule set,
+// spreading each chunk of lines over `threads` threads. `on_verdicts` gets
+// the 1-based line number, the line and its verdicts, always in input order.
+pub fn classify_lines<R, F>(
+    reader: R,
+    rule_sets: &[RuleSet],
+    threads: usize,
+    mut on_verdicts: F,
+) -> io::Result<Counts>
+where
+    R: BufRead,
+    F: FnMut(usize, &str, &[StringType]) -> io::Result<()>,
+{
+    let mut counts = Counts {
+        lines: 0,
+        nice: vec![0; rule_sets.len()],
+    };
+    let mut lines = reader.lines();
+    let mut chunk: Vec<String> = Vec::with_capacity(CHUNK_SIZE);
+
+    loop {
+        chunk.clear();
+        for line in lines.by_ref().take(CHUNK_SIZE) {
+            chunk.push(line?);
+        }
+        if chunk.is_empty() {
+            return Ok(counts);
+        }
+
+        for (line, verdicts) in chunk.iter().zip(classify_chunk(rule_sets, &chunk, threads)) {
+            counts.lines += 1;
+            for (nice, verdict) in counts.nice.iter_mut().zip(verdicts.iter()) {
+                if *verdict == StringType::Nice {
+                    *nice += 1;
+                }
+            }
+            on_verdicts(counts.lines, line, &verdicts)?;
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Per-line verdicts as CSV, one column per rule set.
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(mut writer: W, rule_sets: &[RuleSet]) -> io::Result<Self> {
+        write!(writer, "line,string")?;
+        for rule_set in rule_sets.iter() {
+            write!(writer, ",{}", csv_field(&rule_set.name))?;
+        }
+        writeln!(writer)?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn write_row(&mut self, line: usize, s: &str, verdicts: &[StringType]) -> io::Result<()> {
+        write!(self.writer, "{},{}", line, csv_field(s))?;
+        for verdict in verdicts.iter() {
+            let verdict = match verdict {
+                StringType::Nice => "nice",
+                StringType::Naughty => "naughty",
+            };
+            write!(self.writer, ",{}", verdict)?;
+        }
+        writeln!(self.writer)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nice_string::{check_string, check_string_v2, rule_set, rule_set_v2};
+    use std::io::Cursor;
+
+    const INPUT: &str = "ugknbfddgicrmopn
+aaa
+jchzalrnumimnmhp
+qjhvhtzxzqqjkmpb
+xxyxx
+uurcxstgmygtbstg
+";
+
+    #[test]
+    fn test_classify_lines() {
+        let rule_sets = [rule_set(), rule_set_v2()];
+        let mut seen: Vec<(usize, String)> = Vec::new();
+        let counts = classify_lines(Cursor::new(INPUT), &rule_sets, 1, |line, s, verdicts| {
+            assert_eq!(verdicts, [check_string(s), check_string_v2(s)]);
+            seen.push((line, s.to_string()));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 6,
+                nice: vec![2, 2],
+            }
+        );
+        assert_eq!(seen[2], (3, "jchzalrnumimnmhp".to_string()));
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let input: String = (0..20_000)
+            .map(|index| format!("{:x}aeixy{}\n", index * 7919, index % 97))
+            .collect();
+        let rule_sets = [rule_set(), rule_set_v2()];
+
+        let mut sequential = Vec::new();
+        let counts = classify_lines(Cursor::new(&input), &rule_sets, 1, |_, _, verdicts| {
+            sequential.push(verdicts.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        let mut parallel = Vec::new();
+        let parallel_counts =
+            classify_lines(Cursor::new(&input), &rule_sets, 4, |_, _, verdicts| {
+                parallel.push(verdicts.to_vec());
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(counts, parallel_counts);
+        assert_eq!(counts.lines, 20_000);
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn test_csv() {
+        let rule_sets = [rule_set(), rule_set_v2()];
+        let mut csv = CsvWriter::new(Vec::new(), &rule_sets).unwrap();
+        classify_lines(
+            Cursor::new("aaa\nxx\"y,x\n"),
+            &rule_sets,
+            2,
+            |line, s, verdicts| csv.write_row(line, s, verdicts),
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv.into_inner()).unwrap(),
+            "line,string,part 1,part 2\n1,aaa,nice,naughty\n2,\"xx\"\"y,x\",naughty,naughty\n"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod classifier;
+pub mod nice_string;
+pub mod report;
+pub mod rule;
@@ -1,31 +1,123 @@
 use std::{
+    env, fs,
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufReader, BufWriter, Error, Write},
+    thread,
 };
 
-mod nice_string;
-use nice_string::*;
+use day_5_rust::{
+    classifier::{classify_lines, CsvWriter},
+    nice_string::{rule_set, rule_set_v2},
+    report::{render_report, report},
+    rule::parse_rule_sets,
+};
+
+const USAGE: &str =
+    "Usage: day_5_rust [--report <string>] [--csv <path>] [--threads <n>] [rules.txt]";
+
+// An optional argument points at a rule config to use instead of the
+// puzzle's two rule sets. `--report <string>` explains the verdicts for
+// that string instead of counting the input, `--csv <path>` also writes the
+// verdict of every line and `--threads <n>` limits the worker threads.
+struct Options {
+    config: Option<String>,
+    report: Option<String>,
+    csv: Option<String>,
+    threads: usize,
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut options = Options {
+        config: None,
+        report: None,
+        csv: None,
+        threads: thread::available_parallelism().map_or(1, |count| count.get()),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.as_slice().first()) {
+            ("--report", Some(value)) => options.report = Some(value.to_string()),
+            ("--csv", Some(value)) => options.csv = Some(value.to_string()),
+            ("--threads", Some(value)) => options.threads = value.parse().ok()?,
+            (flag, _) if flag.starts_with("--") => {
+                println!("Unknown option or missing value {}", flag);
+                return None;
+            }
+            (path, _) if options.config.is_none() => {
+                options.config = Some(path.to_string());
+                continue;
+            }
+            (extra, _) => {
+                println!("Unexpected argument {}", extra);
+                return None;
+            }
+        }
+        args.next();
+    }
+
+    Some(options)
+}
 
 fn main() -> Result<(), Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(options) = parse_options(&args) else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let rule_sets = match options.config {
+        Some(path) => match parse_rule_sets(&fs::read_to_string(path)?) {
+            Ok(rule_sets) => rule_sets,
+            Err(error) => {
+                println!("Invalid rule config at line {}", error);
+                return Ok(());
+            }
+        },
+        None => vec![rule_set(), rule_set_v2()],
+    };
+
+    if let Some(s) = options.report {
+        for rule_set in rule_sets.iter() {
+            print!(
+                "{}: {}",
+                rule_set.name,
+                render_report(&s, &report(rule_set, &s))
+            );
+        }
+        return Ok(());
+    }
+
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
 
-    let lines: Vec<String> = reader.lines().filter_map(Result::ok).collect();
+    let mut csv = match options.csv {
+        Some(path) => Some(CsvWriter::new(
+            BufWriter::new(File::create(path)?),
+            &rule_sets,
+        )?),
+        None => None,
+    };
 
-    let mut nice_string_count = 0;
-    let mut nice_string_v2_count = 0;
-    for s in lines {
-        if let StringType::Nice = check_string(&s) {
-            nice_string_count += 1;
-        }
-        if let StringType::Nice = check_string_v2(&s) {
-            nice_string_v2_count += 1;
-        }
+    let counts = classify_lines(
+        reader,
+        &rule_sets,
+        options.threads,
+        |line, s, verdicts| match csv.as_mut() {
+            Some(csv) => csv.write_row(line, s, verdicts),
+            None => Ok(()),
+        },
+    )?;
+    if let Some(csv) = csv {
+        csv.into_inner().flush()?;
     }
 
-    println!("Nice string count {}", nice_string_count);
-    println!("Nice string v2 count {}", nice_string_v2_count);
+    for (rule_set, nice_string_count) in rule_sets.iter().zip(counts.nice.iter()) {
+        println!(
+            "Nice string count ({}) {}",
+            rule_set.name, nice_string_count
+        );
+    }
 
     Ok(())
 }
-
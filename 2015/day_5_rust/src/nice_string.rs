@@ -1,38 +1,63 @@
 use std::collections::HashMap;
 
-#[derive(Debug)]
+use crate::report::{report, Report};
+use crate::rule::{Rule, RuleSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum StringType {
     Nice,
     Naughty,
 }
 
-pub fn check_string_v2(s: &str) -> StringType {
-    if is_contains_pair_of_letters_without_overlap(s)
-        && is_contains_repeat_letter_with_one_between(s)
-    {
-        return StringType::Nice;
+pub fn rule_set() -> RuleSet {
+    RuleSet {
+        name: "part 1".to_string(),
+        rule: Rule::All(vec![
+            Rule::MinCount {
+                chars: "aeiou".to_string(),
+                count: 3,
+            },
+            Rule::Doubled { count: 1 },
+            Rule::Forbidden(["ab", "cd", "pq", "xy"].map(String::from).to_vec()),
+        ]),
     }
-    return StringType::Naughty;
 }
 
-pub fn check_string(s: &str) -> StringType {
-    if is_contains_at_least_three_vowels(s)
-        && is_contains_at_least_one_doublet(s)
-        && is_not_contains_forbidden_strings(s)
-    {
-        return StringType::Nice;
+pub fn rule_set_v2() -> RuleSet {
+    RuleSet {
+        name: "part 2".to_string(),
+        rule: Rule::All(vec![
+            Rule::RepeatedPair { length: 2 },
+            Rule::GapRepeat { gap: 1 },
+        ]),
     }
-    return StringType::Naughty;
 }
 
-fn get_vowels(s: &str) -> HashMap<char, u8> {
+pub fn check_string_v2(s: &str) -> StringType {
+    rule_set_v2().check(s)
+}
+
+pub fn check_string(s: &str) -> StringType {
+    rule_set().check(s)
+}
+
+// Why `s` got its part 1 verdict, rule by rule.
+pub fn classify_with_report(s: &str) -> Report {
+    report(&rule_set(), s)
+}
+
+pub fn classify_with_report_v2(s: &str) -> Report {
+    report(&rule_set_v2(), s)
+}
+
+pub fn get_vowels(s: &str) -> HashMap<char, u8> {
     let mut vowels: HashMap<char, u8> = HashMap::new();
 
     for c in s.chars() {
         match c {
             'a' | 'e' | 'i' | 'o' | 'u' => {
                 let v = vowels.entry(c).or_insert(0);
-                *v += 1;
+                *v = v.saturating_add(1);
             }
             _ => {}
         }
@@ -41,91 +66,50 @@ fn get_vowels(s: &str) -> HashMap<char, u8> {
     vowels
 }
 
-fn is_contains_at_least_three_vowels(s: &str) -> bool {
-    get_vowels(s).values().sum::<u8>() >= 3
+pub fn is_contains_at_least_three_vowels(s: &str) -> bool {
+    Rule::MinCount {
+        chars: "aeiou".to_string(),
+        count: 3,
+    }
+    .check(s)
 }
 
-fn get_doublets(s: &str) -> HashMap<String, u8> {
+// Counts per doubled grapheme, so `"éé"` is one doublet however the `é` is
+// encoded.
+pub fn get_doublets(s: &str) -> HashMap<String, u8> {
     let mut doublets: HashMap<String, u8> = HashMap::new();
 
-    let chars: Vec<char> = s.chars().collect();
-
-    let mut i: usize = 1;
-    let mut prev: char = chars[0];
-
-    while i < chars.len() {
-        if chars[i] == prev {
-            let key = prev.to_string().repeat(2);
-            let d = doublets.entry(key).or_insert(0);
-            *d += 1;
-            i += 1;
-        }
-
-        prev = if let Some(c) = chars.get(i) {
-            *c
-        } else {
-            break;
-        };
-        i += 1;
+    let spans = Rule::Doubled { count: 0 }.spans(s);
+    for span in spans {
+        let d = doublets.entry(s[span].to_string()).or_insert(0);
+        *d = d.saturating_add(1);
     }
 
     doublets
 }
 
-fn is_contains_at_least_one_doublet(s: &str) -> bool {
-    get_doublets(s).values().sum::<u8>() >= 1
+pub fn is_contains_at_least_one_doublet(s: &str) -> bool {
+    Rule::Doubled { count: 1 }.check(s)
 }
 
-fn is_not_contains_forbidden_strings(s: &str) -> bool {
-    let forbidden_strings: Vec<&str> = vec!["ab", "cd", "pq", "xy"];
-    for f in forbidden_strings.iter() {
-        if s.contains(f) {
-            return false;
-        }
-    }
-    true
+pub fn is_not_contains_forbidden_strings(s: &str) -> bool {
+    Rule::Forbidden(["ab", "cd", "pq", "xy"].map(String::from).to_vec()).check(s)
 }
 
 // part 2
 
-fn is_contains_pair_of_letters_without_overlap(s: &str) -> bool {
-    let mut index: usize = 0;
-    let last_index = s.len();
-
-    while index + 2 < last_index {
-        let pair = &s[index..=index + 1];
-
-        let check = &s[index + 2..last_index];
-        if check.contains(&pair) {
-            return true;
-        } else {
-            index += 1;
-        }
-    }
-    false
+pub fn is_contains_pair_of_letters_without_overlap(s: &str) -> bool {
+    Rule::RepeatedPair { length: 2 }.check(s)
 }
 
-fn is_contains_repeat_letter_with_one_between(s: &str) -> bool {
-    let chars: Vec<char> = s.chars().collect();
-
-    let mut i = 0;
-    let mut a;
-    let mut b;
-
-    while i + 2 < chars.len() {
-        a = chars[i];
-        b = chars[i + 2];
-        if a == b {
-            return true;
-        }
-        i += 1;
-    }
-    false
+pub fn is_contains_repeat_letter_with_one_between(s: &str) -> bool {
+    Rule::GapRepeat { gap: 1 }.check(s)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_get_vowels() {
@@ -136,9 +120,9 @@ mod tests {
 
     #[test]
     fn test_contains_three_vowels() {
-        assert_eq!(is_contains_at_least_three_vowels("aei"), true);
-        assert_eq!(is_contains_at_least_three_vowels("xazegov"), true);
-        assert_eq!(is_contains_at_least_three_vowels("aeiouaeiouaeiou"), true);
+        assert!(is_contains_at_least_three_vowels("aei"));
+        assert!(is_contains_at_least_three_vowels("xazegov"));
+        assert!(is_contains_at_least_three_vowels("aeiouaeiouaeiou"));
     }
 
     #[test]
@@ -146,16 +130,27 @@ mod tests {
         assert_eq!(get_doublets("aaeecc").len(), 3);
     }
 
+    #[test]
+    fn test_get_doublets_unicode() {
+        assert!(get_doublets("").is_empty());
+        assert!(get_doublets("a").is_empty());
+        assert_eq!(get_doublets("ééxaaa").get("éé"), Some(&1));
+        assert_eq!(get_doublets("ééxaaa").get("aa"), Some(&1));
+        // `e` followed by a combining acute accent is one grapheme.
+        assert_eq!(get_doublets("e\u{301}e\u{301}e").len(), 1);
+        assert!(get_doublets("e\u{301}e").is_empty());
+    }
+
     #[test]
     fn test_contains_one_doublet() {
-        assert_eq!(is_contains_at_least_three_vowels("aei"), true);
+        assert!(is_contains_at_least_three_vowels("aei"));
     }
 
     #[test]
     fn test_contains_forbidden_strings() {
-        assert_eq!(is_not_contains_forbidden_strings("abc"), false);
-        assert_eq!(is_not_contains_forbidden_strings("cdd"), false);
-        assert_eq!(is_not_contains_forbidden_strings("aad"), true);
+        assert!(!is_not_contains_forbidden_strings("abc"));
+        assert!(!is_not_contains_forbidden_strings("cdd"));
+        assert!(is_not_contains_forbidden_strings("aad"));
     }
 
     #[test]
@@ -178,36 +173,28 @@ mod tests {
 
     #[test]
     fn test_contains_pair_of_letters_without_overlap() {
-        assert_eq!(is_contains_pair_of_letters_without_overlap("xyxy"), true);
-        assert_eq!(
-            is_contains_pair_of_letters_without_overlap("aabcdefgaa"),
-            true
-        );
-        assert_eq!(is_contains_pair_of_letters_without_overlap("12345"), false);
-        assert_eq!(is_contains_pair_of_letters_without_overlap("aaa"), false);
-        assert_eq!(is_contains_pair_of_letters_without_overlap("aaaa"), true);
-        assert_eq!(is_contains_pair_of_letters_without_overlap("aafaa"), true);
-        assert_eq!(is_contains_pair_of_letters_without_overlap("eefee"), true);
-        assert_eq!(
-            is_contains_pair_of_letters_without_overlap("fwafehfiahewfihfefe"),
-            true
-        );
+        assert!(is_contains_pair_of_letters_without_overlap("xyxy"));
+        assert!(is_contains_pair_of_letters_without_overlap("aabcdefgaa"));
+        assert!(!is_contains_pair_of_letters_without_overlap("12345"));
+        assert!(!is_contains_pair_of_letters_without_overlap("aaa"));
+        assert!(is_contains_pair_of_letters_without_overlap("aaaa"));
+        assert!(is_contains_pair_of_letters_without_overlap("aafaa"));
+        assert!(is_contains_pair_of_letters_without_overlap("eefee"));
+        assert!(is_contains_pair_of_letters_without_overlap(
+            "fwafehfiahewfihfefe"
+        ));
     }
 
     #[test]
     fn test_contains_repeat_letter_with_one_between() {
-        assert_eq!(is_contains_repeat_letter_with_one_between("xyx"), true);
-        assert_eq!(
-            is_contains_repeat_letter_with_one_between("abcdefeghi"),
-            true
-        );
-        assert_eq!(is_contains_repeat_letter_with_one_between("aaa"), true);
-        assert_eq!(is_contains_repeat_letter_with_one_between("aav"), false);
-        assert_eq!(is_contains_repeat_letter_with_one_between("abcdcba"), true);
-        assert_eq!(
-            is_contains_repeat_letter_with_one_between("fwafehfiahewfihfefe"),
-            true
-        );
+        assert!(is_contains_repeat_letter_with_one_between("xyx"));
+        assert!(is_contains_repeat_letter_with_one_between("abcdefeghi"));
+        assert!(is_contains_repeat_letter_with_one_between("aaa"));
+        assert!(!is_contains_repeat_letter_with_one_between("aav"));
+        assert!(is_contains_repeat_letter_with_one_between("abcdcba"));
+        assert!(is_contains_repeat_letter_with_one_between(
+            "fwafehfiahewfihfefe"
+        ));
     }
 
     #[test]
@@ -238,4 +225,45 @@ mod tests {
             StringType::Nice
         ));
     }
+
+    #[test]
+    fn test_unicode_predicates() {
+        assert!(is_contains_pair_of_letters_without_overlap("żółżół"));
+        assert!(!is_contains_pair_of_letters_without_overlap("żżż"));
+        assert!(is_contains_repeat_letter_with_one_between("😀x😀"));
+        assert!(!is_contains_at_least_three_vowels(""));
+        assert!(matches!(check_string(""), StringType::Naughty));
+        assert!(matches!(check_string_v2(""), StringType::Naughty));
+    }
+
+    proptest! {
+        #[test]
+        fn test_predicates_never_panic(s in any::<String>()) {
+            get_vowels(&s);
+            get_doublets(&s);
+            let nice = is_contains_at_least_three_vowels(&s)
+                && is_contains_at_least_one_doublet(&s)
+                && is_not_contains_forbidden_strings(&s);
+            let nice_v2 = is_contains_pair_of_letters_without_overlap(&s)
+                && is_contains_repeat_letter_with_one_between(&s);
+
+            prop_assert_eq!(check_string(&s) == StringType::Nice, nice);
+            prop_assert_eq!(check_string_v2(&s) == StringType::Nice, nice_v2);
+        }
+
+        // Mostly combining marks and repeats, so grapheme clusters and
+        // matches show up far more often than in uniform strings.
+        #[test]
+        fn test_spans_are_on_char_boundaries(s in "[ae\\u{301}\\u{1F600}xy]{0,12}") {
+            for rule in classify_with_report(&s)
+                .rules
+                .iter()
+                .chain(classify_with_report_v2(&s).rules.iter())
+            {
+                for span in rule.spans.iter() {
+                    prop_assert!(s.get(span.clone()).is_some());
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use crate::nice_string::StringType;
+use crate::rule::{Rule, RuleSet};
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleReport {
+    pub rule: Rule,
+    pub passed: bool,
+    // Byte spans into the checked string, see `Rule::spans`.
+    pub spans: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub string_type: StringType,
+    pub rules: Vec<RuleReport>,
+}
+
+// One entry per rule of the set. A set is usually an `all` of its config
+// lines, in which case every line is reported on its own.
+pub fn report(rule_set: &RuleSet, s: &str) -> Report {
+    let rules = match &rule_set.rule {
+        Rule::All(rules) => rules.as_slice(),
+        rule => std::slice::from_ref(rule),
+    };
+
+    Report {
+        string_type: rule_set.check(s),
+        rules: rules
+            .iter()
+            .map(|rule| RuleReport {
+                rule: rule.clone(),
+                passed: rule.check(s),
+                spans: rule.spans(s),
+            })
+            .collect(),
+    }
+}
+
+fn format_spans(spans: &[Range<usize>]) -> String {
+    spans
+        .iter()
+        .map(|span| format!("{}..{}", span.start, span.end))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// The string with every span highlighted, green for rules that passed and
+// red for rules that failed, followed by one line per rule. Where spans of
+// both overlap red wins. Spans are cut off at the end of `s`, in case the
+// report was made for another string.
+pub fn render_report(s: &str, report: &Report) -> String {
+    let mut colors: Vec<Option<&str>> = vec![None; s.len()];
+    for rule in report.rules.iter() {
+        let color = if rule.passed { GREEN } else { RED };
+        for span in rule.spans.iter() {
+            let end = span.end.min(s.len());
+            for slot in colors[span.start.min(end)..end].iter_mut() {
+                if *slot != Some(RED) {
+                    *slot = Some(color);
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    let mut current: Option<&str> = None;
+    for (index, c) in s.char_indices() {
+        if colors[index] != current {
+            output.push_str(colors[index].unwrap_or(RESET));
+            current = colors[index];
+        }
+        output.push(c);
+    }
+    if current.is_some() {
+        output.push_str(RESET);
+    }
+
+    let verdict = match report.string_type {
+        StringType::Nice => format!("{}nice{}", GREEN, RESET),
+        StringType::Naughty => format!("{}naughty{}", RED, RESET),
+    };
+    output.push_str(&format!(" is {}\n", verdict));
+
+    for rule in report.rules.iter() {
+        let status = if rule.passed {
+            format!("{}pass{}", GREEN, RESET)
+        } else {
+            format!("{}fail{}", RED, RESET)
+        };
+        output.push_str(&format!("  {} {}", status, rule.rule));
+        if !rule.spans.is_empty() {
+            output.push_str(&format!(" at {}", format_spans(&rule.spans)));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nice_string::{classify_with_report, classify_with_report_v2};
+
+    #[test]
+    fn test_report_spans() {
+        let report = classify_with_report("haegwjzuvuyypxyu");
+
+        assert_eq!(report.string_type, StringType::Naughty);
+        assert_eq!(report.rules.len(), 3);
+        assert!(report.rules[0].passed);
+        assert_eq!(report.rules[1].spans, vec![10..12]);
+        assert!(!report.rules[2].passed);
+        assert_eq!(report.rules[2].spans, vec![13..15]);
+
+        let report = classify_with_report("abcxy");
+        assert_eq!(report.rules[2].spans, vec![0..2, 3..5]);
+
+        let report = classify_with_report_v2("qjhvhtzxzqqjkmpb");
+        assert_eq!(report.string_type, StringType::Nice);
+        assert_eq!(report.rules[0].spans, vec![0..2, 10..12]);
+        assert_eq!(report.rules[1].spans, vec![2..5, 6..9]);
+    }
+
+    #[test]
+    fn test_report_spans_are_bytes() {
+        let report = classify_with_report("ééxyaa");
+
+        assert_eq!(report.rules[1].spans, vec![0..4, 6..8]);
+        assert_eq!(report.rules[2].spans, vec![4..6]);
+    }
+
+    #[test]
+    fn test_render_report() {
+        let s = "aexyi";
+        let rendered = render_report(s, &classify_with_report(s));
+
+        assert_eq!(
+            rendered.lines().next(),
+            Some("\x1b[32mae\x1b[31mxy\x1b[32mi\x1b[0m is \x1b[31mnaughty\x1b[0m")
+        );
+        assert!(rendered.contains("\x1b[31mfail\x1b[0m doubled 1\n"));
+        assert!(rendered.contains("\x1b[31mfail\x1b[0m forbid ab cd pq xy at 2..4\n"));
+    }
+
+    #[test]
+    fn test_render_report_for_other_string() {
+        let report = classify_with_report("aaaaaaaaxy");
+
+        let rendered = render_report("aaaaaaaax", &report);
+        assert!(rendered.starts_with("\x1b[32maaaaaaaa\x1b[31mx\x1b[0m is"));
+        assert!(render_report("", &report).starts_with(" is"));
+    }
+}
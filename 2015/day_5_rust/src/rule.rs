@@ -0,0 +1,570 @@
+use std::{fmt, ops::Range};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::nice_string::StringType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+    // At least `count` characters out of `chars`.
+    MinCount { chars: String, count: usize },
+    // At least `count` letters appearing twice in a row, like `dd`.
+    Doubled { count: usize },
+    // None of the strings may appear.
+    Forbidden(Vec<String>),
+    // Some run of `length` characters appears twice without overlapping.
+    RepeatedPair { length: usize },
+    // Some character repeats with exactly `gap` characters between.
+    GapRepeat { gap: usize },
+}
+
+// `s` split into grapheme clusters, keeping each one's byte offset so rules
+// compare what a reader sees as a character and still point into the
+// original string.
+struct Text<'a> {
+    s: &'a str,
+    offsets: Vec<usize>,
+    graphemes: Vec<&'a str>,
+}
+
+impl<'a> Text<'a> {
+    fn new(s: &'a str) -> Self {
+        let (offsets, graphemes) = s.grapheme_indices(true).unzip();
+        Self {
+            s,
+            offsets,
+            graphemes,
+        }
+    }
+
+    fn is_boundary(&self, offset: usize) -> bool {
+        offset == self.s.len() || self.offsets.binary_search(&offset).is_ok()
+    }
+
+    // Byte range of `count` graphemes starting at grapheme `from`.
+    fn span(&self, from: usize, count: usize) -> Range<usize> {
+        let end = self
+            .offsets
+            .get(from + count)
+            .copied()
+            .unwrap_or(self.s.len());
+        self.offsets[from]..end
+    }
+}
+
+impl Rule {
+    pub fn check(&self, s: &str) -> bool {
+        self.first_failure(s).is_none()
+    }
+
+    // The rule that made `s` fail. `All` points at its first failing part,
+    // the other combinators can only be blamed as a whole.
+    pub fn first_failure(&self, s: &str) -> Option<&Rule> {
+        self.first_failure_in(&Text::new(s))
+    }
+
+    // Byte spans of what this rule found in `s`: the matches that count
+    // towards it, or for `forbid` the occurrences that break it.
+    pub fn spans(&self, s: &str) -> Vec<Range<usize>> {
+        self.spans_in(&Text::new(s))
+    }
+
+    fn spans_in(&self, text: &Text) -> Vec<Range<usize>> {
+        let graphemes = &text.graphemes;
+
+        match self {
+            Rule::All(rules) | Rule::Any(rules) => {
+                rules.iter().flat_map(|rule| rule.spans_in(text)).collect()
+            }
+            Rule::Not(rule) => rule.spans_in(text),
+            Rule::MinCount { chars, .. } => (0..graphemes.len())
+                .filter(|index| chars.graphemes(true).any(|c| c == graphemes[*index]))
+                .map(|index| text.span(index, 1))
+                .collect(),
+            Rule::Doubled { .. } => {
+                let mut spans = Vec::new();
+                let mut index = 1;
+                while index < graphemes.len() {
+                    if graphemes[index] == graphemes[index - 1] {
+                        spans.push(text.span(index - 1, 2));
+                        index += 1;
+                    }
+                    index += 1;
+                }
+                spans
+            }
+            Rule::Forbidden(strings) => {
+                let mut spans: Vec<Range<usize>> = strings
+                    .iter()
+                    .flat_map(|f| {
+                        // Only whole graphemes count, `e` must not match the
+                        // start of `e\u{301}`.
+                        text.offsets
+                            .iter()
+                            .filter(|&&start| text.s[start..].starts_with(f.as_str()))
+                            .map(|&start| start..start + f.len())
+                            .filter(|span| text.is_boundary(span.end))
+                            .collect::<Vec<Range<usize>>>()
+                    })
+                    .collect();
+                spans.sort_by_key(|span| (span.start, span.end));
+                spans
+            }
+            Rule::RepeatedPair { length } => {
+                let length = (*length).max(1);
+                graphemes
+                    .windows(length)
+                    .enumerate()
+                    .find_map(|(index, pair)| {
+                        let rest = graphemes.get(index + length..)?;
+                        let other = rest.windows(length).position(|other| other == pair)?;
+                        Some(vec![
+                            text.span(index, length),
+                            text.span(index + length + other, length),
+                        ])
+                    })
+                    .unwrap_or_default()
+            }
+            Rule::GapRepeat { gap } => graphemes
+                .windows(gap.saturating_add(2))
+                .enumerate()
+                .filter(|(_, window)| window[0] == window[gap + 1])
+                .map(|(index, _)| text.span(index, gap + 2))
+                .collect(),
+        }
+    }
+
+    fn first_failure_in(&self, text: &Text) -> Option<&Rule> {
+        let passes = match self {
+            Rule::All(rules) => {
+                return rules.iter().find_map(|rule| rule.first_failure_in(text));
+            }
+            Rule::Any(rules) => rules
+                .iter()
+                .any(|rule| rule.first_failure_in(text).is_none()),
+            Rule::Not(rule) => rule.first_failure_in(text).is_some(),
+            Rule::MinCount { count, .. } | Rule::Doubled { count } => {
+                self.spans_in(text).len() >= *count
+            }
+            Rule::Forbidden(_) => self.spans_in(text).is_empty(),
+            Rule::RepeatedPair { .. } | Rule::GapRepeat { .. } => !self.spans_in(text).is_empty(),
+        };
+
+        if passes {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, name: &str, rules: &[Rule]) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    for (index, rule) in rules.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", rule)?;
+    }
+    write!(f, ")")
+}
+
+// Same syntax as the config, so a printed rule can be read back.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::All(rules) => write_list(f, "all", rules),
+            Rule::Any(rules) => write_list(f, "any", rules),
+            Rule::Not(rule) => write!(f, "not({})", rule),
+            Rule::MinCount { chars, count } => write!(f, "min-count {} {}", count, chars),
+            Rule::Doubled { count } => write!(f, "doubled {}", count),
+            Rule::Forbidden(strings) => write!(f, "forbid {}", strings.join(" ")),
+            Rule::RepeatedPair { length } => write!(f, "repeated-pair {}", length),
+            Rule::GapRepeat { gap } => write!(f, "gap-repeat {}", gap),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSet {
+    pub name: String,
+    pub rule: Rule,
+}
+
+impl RuleSet {
+    pub fn check(&self, s: &str) -> StringType {
+        if self.rule.check(s) {
+            StringType::Nice
+        } else {
+            StringType::Naughty
+        }
+    }
+
+    pub fn explain(&self, s: &str) -> Option<String> {
+        self.rule
+            .first_failure(s)
+            .map(|rule| format!("{}: `{}` failed", self.name, rule))
+    }
+}
+
+// Verdicts of every set for `s`, splitting it into graphemes only once.
+pub fn check_all(rule_sets: &[RuleSet], s: &str) -> Vec<StringType> {
+    let text = Text::new(s);
+
+    rule_sets
+        .iter()
+        .map(|rule_set| match rule_set.rule.first_failure_in(&text) {
+            None => StringType::Nice,
+            Some(_) => StringType::Naughty,
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    UnexpectedToken {
+        line: usize,
+        expected: &'static str,
+        found: Option<String>,
+    },
+    RuleOutsideSet {
+        line: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnexpectedToken {
+                line,
+                expected,
+                found,
+            } => match found {
+                Some(found) => write!(f, "{}: expected {}, found `{}`", line, expected, found),
+                None => write!(f, "{}: expected {}, found end of line", line, expected),
+            },
+            ConfigError::RuleOutsideSet { line } => {
+                write!(f, "{}: rule before the first [rule set] header", line)
+            }
+        }
+    }
+}
+
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens: Vec<&str> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (index, c) in line.char_indices() {
+        let is_symbol = matches!(c, '(' | ')' | ',');
+        if c.is_whitespace() || is_symbol {
+            if let Some(from) = start.take() {
+                tokens.push(&line[from..index]);
+            }
+            if is_symbol {
+                tokens.push(&line[index..index + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(from) = start {
+        tokens.push(&line[from..]);
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn error(&self, expected: &'static str) -> ConfigError {
+        ConfigError::UnexpectedToken {
+            line: self.line,
+            expected,
+            found: self.peek().map(str::to_string),
+        }
+    }
+
+    fn expect(&mut self, token: &'static str, expected: &'static str) -> Result<(), ConfigError> {
+        if self.peek() != Some(token) {
+            return Err(self.error(expected));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<usize, ConfigError> {
+        match self.peek().and_then(|token| token.parse().ok()) {
+            Some(number) => {
+                self.position += 1;
+                Ok(number)
+            }
+            None => Err(self.error("number")),
+        }
+    }
+
+    fn word(&mut self) -> Result<&'a str, ConfigError> {
+        match self.peek() {
+            Some(token) if !matches!(token, "(" | ")" | ",") => {
+                self.position += 1;
+                Ok(token)
+            }
+            _ => Err(self.error("characters")),
+        }
+    }
+
+    fn rule_list(&mut self) -> Result<Vec<Rule>, ConfigError> {
+        self.expect("(", "`(`")?;
+        let mut rules = vec![self.rule()?];
+        while self.peek() == Some(",") {
+            self.position += 1;
+            rules.push(self.rule()?);
+        }
+        self.expect(")", "`,` or `)`")?;
+        Ok(rules)
+    }
+
+    fn rule(&mut self) -> Result<Rule, ConfigError> {
+        const EXPECTED: &str =
+            "rule (all, any, not, min-count, doubled, forbid, repeated-pair, gap-repeat)";
+
+        let name = self.peek().ok_or_else(|| self.error(EXPECTED))?;
+        self.position += 1;
+
+        let rule = match name {
+            "all" => Rule::All(self.rule_list()?),
+            "any" => Rule::Any(self.rule_list()?),
+            "not" => {
+                self.expect("(", "`(`")?;
+                let rule = self.rule()?;
+                self.expect(")", "`)`")?;
+                Rule::Not(Box::new(rule))
+            }
+            "min-count" => {
+                let count = self.number()?;
+                let chars = self.word()?.to_string();
+                Rule::MinCount { chars, count }
+            }
+            "doubled" => Rule::Doubled {
+                count: self.number()?,
+            },
+            "forbid" => {
+                let mut strings = vec![self.word()?.to_string()];
+                while let Ok(word) = self.word() {
+                    strings.push(word.to_string());
+                }
+                Rule::Forbidden(strings)
+            }
+            "repeated-pair" => Rule::RepeatedPair {
+                length: self.number()?,
+            },
+            "gap-repeat" => Rule::GapRepeat {
+                gap: self.number()?,
+            },
+            _ => {
+                self.position -= 1;
+                return Err(self.error(EXPECTED));
+            }
+        };
+
+        Ok(rule)
+    }
+}
+
+pub fn parse_rule(s: &str) -> Result<Rule, ConfigError> {
+    parse_rule_line(s, 1)
+}
+
+fn parse_rule_line(s: &str, line: usize) -> Result<Rule, ConfigError> {
+    let mut parser = Parser {
+        tokens: tokenize(s),
+        position: 0,
+        line,
+    };
+    let rule = parser.rule()?;
+
+    if parser.peek().is_some() {
+        return Err(parser.error("end of line"));
+    }
+
+    Ok(rule)
+}
+
+// A `[name]` header starts a rule set, every following line until the next
+// header is one rule that has to pass. Blank lines and `#` comments are
+// skipped.
+pub fn parse_rule_sets(config: &str) -> Result<Vec<RuleSet>, ConfigError> {
+    let mut sets: Vec<RuleSet> = Vec::new();
+
+    for (index, line) in config.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sets.push(RuleSet {
+                name: name.trim().to_string(),
+                rule: Rule::All(Vec::new()),
+            });
+            continue;
+        }
+
+        let rule = parse_rule_line(line, index + 1)?;
+        match sets.last_mut() {
+            Some(RuleSet {
+                rule: Rule::All(rules),
+                ..
+            }) => rules.push(rule),
+            _ => return Err(ConfigError::RuleOutsideSet { line: index + 1 }),
+        }
+    }
+
+    Ok(sets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const CONFIG: &str = "
+# 2015 day 5
+[part 1]
+min-count 3 aeiou
+doubled 1
+forbid ab cd pq xy
+
+[part 2]
+repeated-pair 2
+gap-repeat 1
+";
+
+    #[test]
+    fn test_primitives() {
+        let vowels = parse_rule("min-count 3 aeiou").unwrap();
+        assert!(vowels.check("xazegov"));
+        assert!(!vowels.check("dvszwmarrgswjxmb"));
+
+        assert!(Rule::Doubled { count: 2 }.check("aabb"));
+        assert!(!Rule::Doubled { count: 2 }.check("aaa"));
+        assert!(!Rule::Doubled { count: 1 }.check(""));
+
+        assert!(Rule::RepeatedPair { length: 2 }.check("aaaa"));
+        assert!(!Rule::RepeatedPair { length: 2 }.check("aaa"));
+        assert!(Rule::RepeatedPair { length: 3 }.check("abcxabc"));
+
+        assert!(Rule::GapRepeat { gap: 0 }.check("xx"));
+        assert!(Rule::GapRepeat { gap: 2 }.check("abca"));
+        assert!(!Rule::GapRepeat { gap: 2 }.check("aba"));
+    }
+
+    #[test]
+    fn test_forbidden_graphemes() {
+        let forbid_e = Rule::Forbidden(vec!["e".to_string()]);
+
+        assert!(forbid_e.check("e\u{301}"));
+        assert!(!forbid_e.check("e\u{301}e"));
+        assert_eq!(forbid_e.spans("e\u{301}e"), vec![3..4]);
+        assert!(!Rule::Forbidden(vec!["e\u{301}".to_string()]).check("xe\u{301}"));
+        assert_eq!(
+            Rule::Forbidden(vec!["aa".to_string()]).spans("aa\u{301}aa"),
+            vec![4..6]
+        );
+    }
+
+    #[test]
+    fn test_combinators() {
+        let rule = parse_rule("any(not(forbid xy), all(gap-repeat 1, doubled 1))").unwrap();
+
+        assert!(rule.check("axyb"));
+        assert!(rule.check("abaa"));
+        assert!(!rule.check("aba"));
+        assert_eq!(rule.first_failure("aba"), Some(&rule));
+        assert_eq!(
+            rule.to_string(),
+            "any(not(forbid xy), all(gap-repeat 1, doubled 1))"
+        );
+    }
+
+    #[test]
+    fn test_rule_sets() {
+        let sets = parse_rule_sets(CONFIG).unwrap();
+
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].name, "part 1");
+        assert!(matches!(
+            sets[0].check("ugknbfddgicrmopn"),
+            StringType::Nice
+        ));
+        assert!(matches!(
+            sets[1].check("qjhvhtzxzqqjkmpb"),
+            StringType::Nice
+        ));
+        assert_eq!(sets[0].explain("ugknbfddgicrmopn"), None);
+        assert_eq!(
+            sets[0].explain("haegwjzuvuyypxyu"),
+            Some("part 1: `forbid ab cd pq xy` failed".to_string())
+        );
+        assert_eq!(
+            sets[1].explain("ieodomkazucvgmuy"),
+            Some("part 2: `repeated-pair 2` failed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_config_errors() {
+        assert_eq!(
+            parse_rule_sets("doubled 1"),
+            Err(ConfigError::RuleOutsideSet { line: 1 })
+        );
+        assert_eq!(
+            parse_rule_sets("[a]\ndoubled x"),
+            Err(ConfigError::UnexpectedToken {
+                line: 2,
+                expected: "number",
+                found: Some("x".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_rule("all(doubled 1").unwrap_err().to_string(),
+            "1: expected `,` or `)`, found end of line"
+        );
+        assert!(parse_rule("tripled 1").is_err());
+        assert!(parse_rule("doubled 1 2").is_err());
+        assert!(parse_rule("forbid").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_never_panics(s in any::<String>(), n in any::<usize>(), chars in any::<String>()) {
+            let rule = Rule::Any(vec![
+                Rule::MinCount { chars, count: n },
+                Rule::Doubled { count: n },
+                Rule::Forbidden(vec![s.clone(), String::new()]),
+                Rule::RepeatedPair { length: n },
+                Rule::GapRepeat { gap: n },
+                Rule::RepeatedPair { length: n % 4 },
+                Rule::GapRepeat { gap: n % 4 },
+            ]);
+
+            rule.check(&s);
+            for span in rule.spans(&s) {
+                prop_assert!(s.get(span).is_some());
+            }
+            let _ = parse_rule(&s);
+            let _ = parse_rule_sets(&s);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{fs::File, io::BufWriter, io::Write};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, DynamicImage, Frame, ImageError, RgbImage,
+};
+
+use crate::color_map::{ColorMap, Scale};
+use crate::command::Command;
+use crate::light_rule::LightRule;
+use crate::lights::Lights;
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Io(std::io::Error),
+    Gif(ImageError),
+    Apng(png::EncodingError),
+}
+
+impl From<std::io::Error> for AnimationError {
+    fn from(error: std::io::Error) -> Self {
+        AnimationError::Io(error)
+    }
+}
+
+impl From<ImageError> for AnimationError {
+    fn from(error: ImageError) -> Self {
+        AnimationError::Gif(error)
+    }
+}
+
+impl From<png::EncodingError> for AnimationError {
+    fn from(error: png::EncodingError) -> Self {
+        AnimationError::Apng(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    pub frame_every: usize,
+    pub frame_rate: u16,
+    pub downscale: usize,
+    pub color_map: ColorMap,
+    pub scale: Scale,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::Gif,
+            frame_every: 1,
+            frame_rate: 10,
+            downscale: 1,
+            color_map: ColorMap::Greyscale,
+            scale: Scale::Linear,
+        }
+    }
+}
+
+impl AnimationOptions {
+    fn frame_every(&self) -> usize {
+        self.frame_every.max(1)
+    }
+
+    fn downscale(&self) -> usize {
+        self.downscale.max(1)
+    }
+
+    fn frame_rate(&self) -> u16 {
+        self.frame_rate.max(1)
+    }
+
+    // A frame is taken after every `frame_every` commands and after the last
+    // one, so the animation always ends on the final pattern.
+    pub fn frame_count(&self, commands: usize) -> usize {
+        commands.div_ceil(self.frame_every()).max(1)
+    }
+
+    fn is_frame(&self, executed: usize, commands: usize) -> bool {
+        executed == commands || executed.is_multiple_of(self.frame_every())
+    }
+}
+
+fn render_frame<R: LightRule<Cell = u32>>(
+    lights: &Lights<R>,
+    options: &AnimationOptions,
+    max_brightness: u32,
+) -> RgbImage {
+    let downscale = options.downscale();
+    let side = lights.size.div_ceil(downscale);
+    let mut img: RgbImage = RgbImage::new(side as u32, side as u32);
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (x_start, y_start) = (x as usize * downscale, y as usize * downscale);
+        let x_end = (x_start + downscale).min(lights.size);
+        let y_end = (y_start + downscale).min(lights.size);
+
+        let mut sum = 0_u64;
+        for row in y_start..y_end {
+            for light in
+                lights.grid.cells()[row * lights.size + x_start..row * lights.size + x_end].iter()
+            {
+                sum += *light as u64;
+            }
+        }
+        let average = sum as f32 / ((x_end - x_start) * (y_end - y_start)) as f32;
+
+        *pixel = options
+            .color_map
+            .color(options.scale.normalize(average, max_brightness as f32));
+    }
+
+    img
+}
+
+// Runs the commands twice: once to find the brightest light over all frames
+// so the colours do not shift between frames, and once to render them.
+fn render_frames<R, F>(
+    rule: R,
+    commands: &[Command],
+    options: &AnimationOptions,
+    mut on_frame: F,
+) -> Result<(), AnimationError>
+where
+    R: LightRule<Cell = u32> + Clone,
+    F: FnMut(RgbImage) -> Result<(), AnimationError>,
+{
+    let mut lights = Lights::new(rule.clone());
+    let mut max_brightness = 0;
+    for (index, command) in commands.iter().enumerate() {
+        lights.exec(command);
+        if options.is_frame(index + 1, commands.len()) {
+            max_brightness = max_brightness.max(*lights.grid.cells().iter().max().unwrap_or(&0));
+        }
+    }
+
+    let mut lights = Lights::new(rule);
+    if commands.is_empty() {
+        return on_frame(render_frame(&lights, options, max_brightness));
+    }
+    for (index, command) in commands.iter().enumerate() {
+        lights.exec(command);
+        if options.is_frame(index + 1, commands.len()) {
+            on_frame(render_frame(&lights, options, max_brightness))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_gif<R, W>(
+    rule: R,
+    commands: &[Command],
+    options: &AnimationOptions,
+    writer: W,
+) -> Result<(), AnimationError>
+where
+    R: LightRule<Cell = u32> + Clone,
+    W: Write,
+{
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(1000, options.frame_rate() as u32);
+
+    render_frames(rule, commands, options, |img| {
+        let img = DynamicImage::ImageRgb8(img).into_rgba8();
+        encoder.encode_frame(Frame::from_parts(img, 0, 0, delay))?;
+        Ok(())
+    })
+}
+
+pub fn write_apng<R, W>(
+    rule: R,
+    commands: &[Command],
+    options: &AnimationOptions,
+    writer: W,
+) -> Result<(), AnimationError>
+where
+    R: LightRule<Cell = u32> + Clone,
+    W: Write,
+{
+    let side = Lights::new(rule.clone()).size.div_ceil(options.downscale()) as u32;
+    let mut encoder = png::Encoder::new(writer, side, side);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(options.frame_count(commands.len()) as u32, 0)?;
+    encoder.set_frame_delay(1, options.frame_rate())?;
+    let mut png_writer = encoder.write_header()?;
+
+    render_frames(rule, commands, options, |img| {
+        png_writer.write_image_data(img.as_raw())?;
+        Ok(())
+    })?;
+
+    png_writer.finish()?;
+    Ok(())
+}
+
+pub fn save_animation<R: LightRule<Cell = u32> + Clone>(
+    rule: R,
+    commands: &[Command],
+    options: &AnimationOptions,
+    filename: &str,
+) -> Result<(), AnimationError> {
+    let writer = BufWriter::new(File::create(filename)?);
+
+    match options.format {
+        AnimationFormat::Gif => write_gif(rule, commands, options, writer),
+        AnimationFormat::Apng => write_apng(rule, commands, options, writer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_rule::{BinaryRule, BrightnessRule};
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+    use std::io::Cursor;
+
+    fn test_commands() -> Vec<Command> {
+        [
+            "turn on 0,0 through 499,499",
+            "toggle 0,0 through 999,999",
+            "turn off 500,500 through 999,999",
+        ]
+        .iter()
+        .map(|line| Command::new(line).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_frame_count() {
+        let options = AnimationOptions {
+            frame_every: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(options.frame_count(0), 1);
+        assert_eq!(options.frame_count(3), 2);
+        assert_eq!(options.frame_count(4), 2);
+        assert_eq!(AnimationOptions::default().frame_count(3), 3);
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let options = AnimationOptions {
+            downscale: 100,
+            ..Default::default()
+        };
+        let mut gif: Vec<u8> = Vec::new();
+
+        write_gif(BinaryRule, &test_commands(), &options, &mut gif).expect("Should write gif");
+
+        let frames = GifDecoder::new(Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].buffer().dimensions(), (10, 10));
+        assert_eq!(frames[0].buffer().get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(frames[1].buffer().get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(frames[1].buffer().get_pixel(9, 9).0, [255, 255, 255, 255]);
+        assert_eq!(frames[2].buffer().get_pixel(9, 9).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_write_apng() {
+        let options = AnimationOptions {
+            format: AnimationFormat::Apng,
+            frame_every: 2,
+            downscale: 300,
+            color_map: ColorMap::Heat,
+            ..Default::default()
+        };
+        let mut apng: Vec<u8> = Vec::new();
+
+        write_apng(BrightnessRule, &test_commands(), &options, &mut apng)
+            .expect("Should write apng");
+
+        let mut reader = png::Decoder::new(Cursor::new(apng)).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!(control.num_frames, 2);
+        assert_eq!(reader.info().width, 4);
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer).unwrap();
+        // Brightness 3 is the maximum of the whole animation.
+        assert_eq!(buffer[..3], [255, 255, 255]);
+        reader.next_frame(&mut buffer).unwrap();
+        assert_eq!(buffer[..3], [255, 255, 255]);
+        // The bottom-right 100x100 block went from 2 to 1 brightness.
+        let last = buffer.len() - 3;
+        assert_eq!(buffer[last..], [255, 0, 0]);
+    }
+}
@@ -0,0 +1,155 @@
+use image::Rgb;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorMap {
+    Greyscale,
+    Heat,
+    Viridis,
+    Inferno,
+    Binary { off: Rgb<u8>, on: Rgb<u8> },
+}
+
+fn parse_hex_color(hex: &str) -> Option<Rgb<u8>> {
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+    Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+impl ColorMap {
+    // Accepts the map names plus `binary:RRGGBB:RRGGBB` for a binary map
+    // with custom off and on colours.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(colors) = name.strip_prefix("binary:") {
+            let (off, on) = colors.split_once(':')?;
+            return Some(ColorMap::Binary {
+                off: parse_hex_color(off)?,
+                on: parse_hex_color(on)?,
+            });
+        }
+
+        match name {
+            "grey" => Some(ColorMap::Greyscale),
+            "heat" => Some(ColorMap::Heat),
+            "viridis" => Some(ColorMap::Viridis),
+            "inferno" => Some(ColorMap::Inferno),
+            "binary" => Some(ColorMap::Binary {
+                off: Rgb([0, 0, 0]),
+                on: Rgb([255, 255, 255]),
+            }),
+            _ => None,
+        }
+    }
+
+    // Colours picked at evenly spaced points between no light and the
+    // brightest light.
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            ColorMap::Greyscale => &[[0, 0, 0], [255, 255, 255]],
+            ColorMap::Heat => &[[0, 0, 0], [255, 0, 0], [255, 255, 0], [255, 255, 255]],
+            ColorMap::Viridis => &[
+                [68, 1, 84],
+                [72, 40, 120],
+                [62, 73, 137],
+                [49, 104, 142],
+                [38, 130, 142],
+                [31, 158, 137],
+                [53, 183, 121],
+                [110, 206, 88],
+                [181, 222, 43],
+                [253, 231, 37],
+            ],
+            ColorMap::Inferno => &[
+                [0, 0, 4],
+                [27, 12, 65],
+                [74, 12, 107],
+                [120, 28, 109],
+                [165, 44, 96],
+                [207, 68, 70],
+                [237, 105, 37],
+                [251, 155, 6],
+                [247, 209, 61],
+                [252, 255, 164],
+            ],
+            ColorMap::Binary { .. } => &[],
+        }
+    }
+
+    pub fn color(&self, value: f32) -> Rgb<u8> {
+        if let ColorMap::Binary { off, on } = self {
+            return if value > 0.0 { *on } else { *off };
+        }
+
+        let stops = self.stops();
+        let last = stops.len() - 1;
+        let position = value.clamp(0.0, 1.0) * last as f32;
+        let index = (position.floor() as usize).min(last - 1);
+        let (from, to) = (stops[index], stops[index + 1]);
+        let t = position - index as f32;
+
+        Rgb([0, 1, 2].map(|channel| {
+            (from[channel] as f32 + (to[channel] as f32 - from[channel] as f32) * t).round() as u8
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    Linear,
+    Log,
+}
+
+impl Scale {
+    // Maps a brightness onto 0..=1 relative to `max`. A grid that is all off
+    // has nothing to scale against and stays at the bottom of the map.
+    pub fn normalize(&self, value: f32, max: f32) -> f32 {
+        if max <= 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            Scale::Linear => value / max,
+            Scale::Log => value.ln_1p() / max.ln_1p(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_map() {
+        let heat = ColorMap::Heat;
+
+        assert_eq!(heat.color(0.0), Rgb([0, 0, 0]));
+        assert_eq!(heat.color(0.5), Rgb([255, 128, 0]));
+        assert_eq!(heat.color(1.0), Rgb([255, 255, 255]));
+        assert_eq!(heat.color(2.0), Rgb([255, 255, 255]));
+        assert_eq!(ColorMap::Greyscale.color(0.5), Rgb([128, 128, 128]));
+        assert_eq!(ColorMap::Viridis.color(0.0), Rgb([68, 1, 84]));
+        assert_eq!(ColorMap::Inferno.color(1.0), Rgb([252, 255, 164]));
+
+        let binary = ColorMap::Binary {
+            off: Rgb([0, 0, 64]),
+            on: Rgb([255, 200, 0]),
+        };
+        assert_eq!(binary.color(0.0), Rgb([0, 0, 64]));
+        assert_eq!(binary.color(0.01), Rgb([255, 200, 0]));
+        assert_eq!(ColorMap::from_name("binary:000040:ffc800"), Some(binary));
+        assert!(ColorMap::from_name("binary:000040").is_none());
+        assert!(ColorMap::from_name("binary:00004g:ffc800").is_none());
+        assert!(ColorMap::from_name("magma").is_none());
+    }
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(Scale::Linear.normalize(0.0, 0.0), 0.0);
+        assert_eq!(Scale::Log.normalize(0.0, 0.0), 0.0);
+        assert_eq!(Scale::Linear.normalize(5.0, 5.0), 1.0);
+        assert_eq!(Scale::Linear.normalize(1.0, 4.0), 0.25);
+        assert_eq!(Scale::Log.normalize(3.0, 15.0), 0.5);
+    }
+}
@@ -1,73 +1,353 @@
-use utils::vector2::*;
-use crate::vector2_ext::*;
+use std::{fmt, ops::Range};
 
-#[derive(Debug)]
+use utils::{rect::Rect, vector2::*};
+
+#[derive(Debug, PartialEq)]
 pub enum CommandCreationError {
-    InvalidOperation,
-    MissingSeparator,
-    InvalidCoords,
+    InvalidCharacter {
+        span: Range<usize>,
+        found: char,
+    },
+    UnexpectedToken {
+        span: Range<usize>,
+        expected: &'static str,
+        found: Option<String>,
+    },
+    NumberOutOfRange {
+        span: Range<usize>,
+        expected: &'static str,
+    },
 }
 
-#[derive(Debug)]
+impl CommandCreationError {
+    // Byte range of the offending part of the line, empty at the end of the
+    // line when something is missing.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            CommandCreationError::InvalidCharacter { span, .. }
+            | CommandCreationError::UnexpectedToken { span, .. }
+            | CommandCreationError::NumberOutOfRange { span, .. } => span.clone(),
+        }
+    }
+}
+
+impl fmt::Display for CommandCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandCreationError::InvalidCharacter { span, found } => {
+                write!(f, "{:?}: unexpected character `{}`", span, found)
+            }
+            CommandCreationError::UnexpectedToken {
+                span,
+                expected,
+                found,
+            } => match found {
+                Some(found) => write!(f, "{:?}: expected {}, found `{}`", span, expected, found),
+                None => write!(f, "{:?}: expected {}, found end of line", span, expected),
+            },
+            CommandCreationError::NumberOutOfRange { span, expected } => {
+                write!(f, "{:?}: expected {}", span, expected)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum CommandOperation {
     TurnOn,
     TurnOff,
     Toggle,
+    Set(u32),
+    Dim(u32),
+    Invert,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect(Rect),
+    Row(i32),
+    Column(i32),
+    Circle { center: Vector2, radius: u32 },
+}
+
+// The circle inside `bounds` as bands of neighbouring rows that cover the
+// same columns. Within each half of the circle a row's span only grows or
+// only shrinks, so the rows sharing a span are consecutive and the end of
+// each band is found by binary search instead of visiting every row. A
+// circle much larger than `bounds` is just a few bands.
+fn circle_bands(center: &Vector2, radius: u32, bounds: &Rect) -> Vec<Rect> {
+    // The radius squared needs more than an i64 near u32::MAX.
+    let radius = radius as i128;
+    let (x, y) = (center.x as i128, center.y as i128);
+    let span = |row: i128| {
+        let dx = (radius * radius - (row - y) * (row - y)).isqrt();
+        let left = (x - dx).max(bounds.start.x as i128);
+        let right = (x + dx).min(bounds.end.x as i128);
+        (left <= right).then_some((left, right))
+    };
+
+    let top = (y - radius).max(bounds.start.y as i128);
+    let bottom = (y + radius).min(bounds.end.y as i128);
+    let mut bands: Vec<Rect> = Vec::new();
+    let mut row = top;
+
+    while row <= bottom {
+        let current = span(row);
+        let half_end = if row < y { (y - 1).min(bottom) } else { bottom };
+        let (mut last, mut past) = (row, half_end + 1);
+        while past - last > 1 {
+            let middle = last + (past - last) / 2;
+            if span(middle) == current {
+                last = middle;
+            } else {
+                past = middle;
+            }
+        }
+
+        if let Some((left, right)) = current {
+            match bands.last_mut() {
+                // The two halves meet in the middle with the same span.
+                Some(band)
+                    if band.start.x as i128 == left
+                        && band.end.x as i128 == right
+                        && band.end.y as i128 == row - 1 =>
+                {
+                    band.end.y = last as i32
+                }
+                _ => bands.push(Rect::new(
+                    Vector2::new(left as i32, row as i32),
+                    Vector2::new(right as i32, last as i32),
+                )),
+            }
+        }
+        row = last + 1;
+    }
+
+    bands
+}
+
+impl Shape {
+    // Disjoint rectangles covering the shape. Rows and columns span the
+    // whole plane, circles are split into one rectangle per row.
+    pub fn rects(&self) -> Vec<Rect> {
+        self.rects_within(&Rect::new(
+            Vector2::new(i32::MIN, i32::MIN),
+            Vector2::new(i32::MAX, i32::MAX),
+        ))
+    }
+
+    // Same as `rects`, clipped to `bounds`.
+    pub fn rects_within(&self, bounds: &Rect) -> Vec<Rect> {
+        let rect = match self {
+            Shape::Rect(rect) => rect.clone(),
+            Shape::Row(y) => Rect::new(Vector2::new(i32::MIN, *y), Vector2::new(i32::MAX, *y)),
+            Shape::Column(x) => Rect::new(Vector2::new(*x, i32::MIN), Vector2::new(*x, i32::MAX)),
+            Shape::Circle { center, radius } => {
+                return circle_bands(center, *radius, bounds);
+            }
+        };
+
+        rect.intersection(bounds).into_iter().collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Command {
     pub operation: CommandOperation,
-    pub start: Vector2,
-    pub end: Vector2,
+    pub shape: Shape,
 }
 
-impl Command {
-    pub fn new(s: &str) -> Result<Self, CommandCreationError> {
-        let trim_from;
-        let operation: CommandOperation;
-
-        if s.starts_with("turn on") {
-            trim_from = 8;
-            operation = CommandOperation::TurnOn
-        } else if s.starts_with("turn off") {
-            trim_from = 9;
-            operation = CommandOperation::TurnOff
-        } else if s.starts_with("toggle") {
-            trim_from = 7;
-            operation = CommandOperation::Toggle
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    Word,
+    Number,
+    Comma,
+}
+
+#[derive(Debug)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    span: Range<usize>,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token<'_>>, CommandCreationError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = if c.is_whitespace() {
+            continue;
+        } else if c == ',' {
+            TokenKind::Comma
+        } else if c.is_ascii_alphabetic() {
+            while chars.next_if(|(_, c)| c.is_ascii_alphabetic()).is_some() {}
+            TokenKind::Word
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit()))
+        {
+            while chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {}
+            TokenKind::Number
         } else {
-            return Err(CommandCreationError::InvalidOperation);
+            return Err(CommandCreationError::InvalidCharacter {
+                span: start..start + c.len_utf8(),
+                found: c,
+            });
         };
 
-        let coords_pair = &s[trim_from..];
+        let end = chars.peek().map_or(s.len(), |(index, _)| *index);
+        tokens.push(Token {
+            kind,
+            text: &s[start..end],
+            span: start..end,
+        });
+    }
+
+    Ok(tokens)
+}
+
+// command   := operation shape
+// operation := "turn" ("on" | "off") | "toggle" | "set" n | "dim" n | "invert"
+// shape     := point "through" point | "row" y | "column" x
+//            | "circle" "at" point "radius" r
+// point     := x "," y
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Result<Self, CommandCreationError> {
+        Ok(Self {
+            tokens: tokenize(s)?,
+            position: 0,
+            end: s.len(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.position)
+    }
 
-        if !coords_pair.contains("through") {
-            return Err(CommandCreationError::MissingSeparator);
+    fn error(&self, expected: &'static str) -> CommandCreationError {
+        let token = self.peek();
+        CommandCreationError::UnexpectedToken {
+            span: token.map_or(self.end..self.end, |t| t.span.clone()),
+            expected,
+            found: token.map(|t| t.text.to_string()),
         }
+    }
 
-        let coords: Vec<&str> = coords_pair.split("through").map(|c| c.trim()).collect();
+    fn keyword(
+        &mut self,
+        keywords: &[&'static str],
+        expected: &'static str,
+    ) -> Result<&'static str, CommandCreationError> {
+        let keyword = match self.peek() {
+            Some(token) if token.kind == TokenKind::Word => keywords
+                .iter()
+                .find(|keyword| token.text.eq_ignore_ascii_case(keyword)),
+            _ => None,
+        };
 
-        if coords.len() != 2 {
-            return Err(CommandCreationError::InvalidCoords);
+        match keyword {
+            Some(keyword) => {
+                self.position += 1;
+                Ok(keyword)
+            }
+            None => Err(self.error(expected)),
         }
+    }
 
-        let start = if let Ok(start) = Vector2::new_from_str(coords[0]) {
-            start
-        } else {
-            return Err(CommandCreationError::InvalidCoords);
+    fn number<T: std::str::FromStr>(
+        &mut self,
+        expected: &'static str,
+    ) -> Result<T, CommandCreationError> {
+        let (text, span) = match self.peek() {
+            Some(token) if token.kind == TokenKind::Number => (token.text, token.span.clone()),
+            _ => return Err(self.error(expected)),
         };
-        let end = if let Ok(end) = Vector2::new_from_str(coords[1]) {
-            end
-        } else {
-            return Err(CommandCreationError::InvalidCoords);
+
+        match text.parse() {
+            Ok(number) => {
+                self.position += 1;
+                Ok(number)
+            }
+            Err(_) => Err(CommandCreationError::NumberOutOfRange { span, expected }),
+        }
+    }
+
+    fn point(&mut self) -> Result<Vector2, CommandCreationError> {
+        let x = self.number("x coordinate")?;
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Comma => self.position += 1,
+            _ => return Err(self.error("`,`")),
+        }
+        let y = self.number("y coordinate")?;
+
+        Ok(Vector2::new(x, y))
+    }
+
+    fn operation(&mut self) -> Result<CommandOperation, CommandCreationError> {
+        let operation = match self.keyword(
+            &["turn", "toggle", "set", "dim", "invert"],
+            "operation (turn on, turn off, toggle, set, dim, invert)",
+        )? {
+            "turn" => match self.keyword(&["on", "off"], "`on` or `off`")? {
+                "on" => CommandOperation::TurnOn,
+                _ => CommandOperation::TurnOff,
+            },
+            "toggle" => CommandOperation::Toggle,
+            "set" => CommandOperation::Set(self.number("brightness")?),
+            "dim" => CommandOperation::Dim(self.number("brightness")?),
+            _ => CommandOperation::Invert,
         };
 
-        Ok(Self {
-            operation,
-            start,
-            end,
-        })
+        Ok(operation)
+    }
+
+    fn shape(&mut self) -> Result<Shape, CommandCreationError> {
+        let shape = match self.peek() {
+            Some(token) if token.kind == TokenKind::Number => {
+                let start = self.point()?;
+                self.keyword(&["through"], "`through`")?;
+                Shape::Rect(Rect::new(start, self.point()?))
+            }
+            _ => match self.keyword(
+                &["row", "column", "circle"],
+                "shape (x,y through x,y, row, column, circle)",
+            )? {
+                "row" => Shape::Row(self.number("row number")?),
+                "column" => Shape::Column(self.number("column number")?),
+                _ => {
+                    self.keyword(&["at"], "`at`")?;
+                    let center = self.point()?;
+                    self.keyword(&["radius"], "`radius`")?;
+                    let radius = self.number("radius")?;
+                    Shape::Circle { center, radius }
+                }
+            },
+        };
+
+        Ok(shape)
+    }
+
+    fn command(&mut self) -> Result<Command, CommandCreationError> {
+        let operation = self.operation()?;
+        let shape = self.shape()?;
+
+        if self.peek().is_some() {
+            return Err(self.error("end of line"));
+        }
+
+        Ok(Command { operation, shape })
+    }
+}
+
+impl Command {
+    pub fn new(s: &str) -> Result<Self, CommandCreationError> {
+        Parser::new(s)?.command()
     }
 }
 
@@ -80,21 +360,32 @@ mod tests {
         assert!(
             matches!(
                 Command::new("keep on 0,0 through 999,999"),
-                Err(CommandCreationError::InvalidOperation)
+                Err(CommandCreationError::UnexpectedToken {
+                    span: Range { start: 0, end: 4 },
+                    ..
+                })
             ),
             "Should return error on invalid operation name"
         );
         assert!(
             matches!(
                 Command::new("turn on 0,0 999,999"),
-                Err(CommandCreationError::MissingSeparator)
+                Err(CommandCreationError::UnexpectedToken {
+                    span: Range { start: 12, end: 15 },
+                    expected: "`through`",
+                    ..
+                })
             ),
             "Should return error on missing separator"
         );
         assert!(
             matches!(
                 Command::new("turn off 0,0 through"),
-                Err(CommandCreationError::InvalidCoords)
+                Err(CommandCreationError::UnexpectedToken {
+                    span: Range { start: 20, end: 20 },
+                    found: None,
+                    ..
+                })
             ),
             "Should return error on invalid coords"
         );
@@ -104,8 +395,10 @@ mod tests {
                 Command::new("turn on 0,0 through 999,999"),
                 Ok(Command {
                     operation: CommandOperation::TurnOn,
-                    start: Vector2 { x: 0, y: 0 },
-                    end: Vector2 { x: 999, y: 999 }
+                    shape: Shape::Rect(Rect {
+                        start: Vector2 { x: 0, y: 0 },
+                        end: Vector2 { x: 999, y: 999 }
+                    })
                 }),
             ),
             "Should create command 1"
@@ -115,8 +408,10 @@ mod tests {
                 Command::new("toggle 0,0 through 999,0"),
                 Ok(Command {
                     operation: CommandOperation::Toggle,
-                    start: Vector2 { x: 0, y: 0 },
-                    end: Vector2 { x: 999, y: 0 }
+                    shape: Shape::Rect(Rect {
+                        start: Vector2 { x: 0, y: 0 },
+                        end: Vector2 { x: 999, y: 0 }
+                    })
                 })
             ),
             "Should create command 2"
@@ -126,11 +421,156 @@ mod tests {
                 Command::new("turn off 499,499 through 500,500"),
                 Ok(Command {
                     operation: CommandOperation::TurnOff,
-                    start: Vector2 { x: 499, y: 499 },
-                    end: Vector2 { x: 500, y: 500 }
+                    shape: Shape::Rect(Rect {
+                        start: Vector2 { x: 499, y: 499 },
+                        end: Vector2 { x: 500, y: 500 }
+                    })
                 })
             ),
             "Should create command 3"
         );
+        assert!(
+            matches!(
+                Command::new("turn on 5,9 through 2,3"),
+                Ok(Command {
+                    shape: Shape::Rect(Rect {
+                        start: Vector2 { x: 2, y: 3 },
+                        end: Vector2 { x: 5, y: 9 }
+                    }),
+                    ..
+                })
+            ),
+            "Should normalise reversed corners"
+        );
+    }
+
+    #[test]
+    fn test_extended_grammar() {
+        assert_eq!(
+            Command::new("  TURN   On\t-3 , 4 THROUGH 5,-6 "),
+            Ok(Command {
+                operation: CommandOperation::TurnOn,
+                shape: Shape::Rect(Rect::new(Vector2::new(-3, 4), Vector2::new(5, -6))),
+            })
+        );
+        assert_eq!(
+            Command::new("set 7 row 3"),
+            Ok(Command {
+                operation: CommandOperation::Set(7),
+                shape: Shape::Row(3),
+            })
+        );
+        assert_eq!(
+            Command::new("dim 2 column -1"),
+            Ok(Command {
+                operation: CommandOperation::Dim(2),
+                shape: Shape::Column(-1),
+            })
+        );
+        assert_eq!(
+            Command::new("invert circle at 10,20 radius 4"),
+            Ok(Command {
+                operation: CommandOperation::Invert,
+                shape: Shape::Circle {
+                    center: Vector2::new(10, 20),
+                    radius: 4,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let errors = [
+            ("turn on 0;0 through 1,1", 9..10),
+            ("turn up 0,0 through 1,1", 5..7),
+            ("set -1 row 0", 4..6),
+            ("dim 99999999999 row 0", 4..15),
+            ("invert circle at 0,0 radius", 27..27),
+            ("toggle row 1 2", 13..14),
+            ("toggle 0,0 through 1,1,", 22..23),
+            ("toggle 0,0 through 1,x", 21..22),
+        ];
+
+        for (line, span) in errors {
+            let error = Command::new(line).expect_err(line);
+            assert_eq!(error.span(), span, "{}: {}", line, error);
+        }
+
+        assert_eq!(
+            Command::new("turn on 0;0 through 1,1"),
+            Err(CommandCreationError::InvalidCharacter {
+                span: 9..10,
+                found: ';',
+            })
+        );
+        assert_eq!(
+            Command::new("set -1 row 0").unwrap_err().to_string(),
+            "4..6: expected brightness"
+        );
+    }
+
+    #[test]
+    fn test_shape_rects() {
+        let circle = Shape::Circle {
+            center: Vector2::new(0, 0),
+            radius: 2,
+        };
+        let rects = circle.rects();
+
+        assert_eq!(rects.len(), 5);
+        assert_eq!(
+            rects.iter().map(Rect::area).sum::<u128>(),
+            1 + 3 + 5 + 3 + 1
+        );
+        assert_eq!(Rect::union_area(&rects), 13);
+        assert_eq!(Shape::Row(5).rects()[0].area(), 1 << 32);
+    }
+
+    #[test]
+    fn test_circle_bands_match_cells() {
+        let bounds = Rect::new(Vector2::new(-3, -20), Vector2::new(40, 12));
+        for radius in 0..25_u32 {
+            let circle = Shape::Circle {
+                center: Vector2::new(7, -4),
+                radius,
+            };
+            let rects = circle.rects_within(&bounds);
+            let r = radius as i64;
+
+            for pos in bounds.cells() {
+                let (dx, dy) = (pos.x as i64 - 7, pos.y as i64 + 4);
+                let inside = dx * dx + dy * dy <= r * r;
+                let covered = rects.iter().filter(|rect| rect.contains(&pos)).count();
+                assert_eq!(covered, inside as usize, "radius {} at {:?}", radius, pos);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shape_rects_within() {
+        let bounds = Rect::new(Vector2::new(0, 0), Vector2::new(999, 999));
+        let huge = Command::new("turn on circle at 0,0 radius 4000000000").unwrap();
+        let rects = huge.shape.rects_within(&bounds);
+
+        assert_eq!(rects, vec![bounds.clone()]);
+        assert_eq!(
+            Shape::Row(5).rects_within(&bounds),
+            vec![Rect::new(Vector2::new(0, 5), Vector2::new(999, 5))]
+        );
+        assert_eq!(Shape::Column(-1).rects_within(&bounds), vec![]);
+
+        // Rows past the edge of the plane used to be clamped onto it.
+        let edge = Shape::Circle {
+            center: Vector2::new(0, i32::MAX),
+            radius: 3,
+        };
+        let rects = edge.rects();
+        assert_eq!(rects.len(), 3);
+        assert_eq!(Rect::union_area(&rects), 1 + 5 + 5 + 7);
+        assert_eq!(
+            rects.iter().map(Rect::area).sum::<u128>(),
+            Rect::union_area(&rects)
+        );
     }
 }
@@ -0,0 +1,311 @@
+use utils::rect::Rect;
+
+use crate::command::*;
+use crate::light_rule::LightRule;
+
+// Lights on an unbounded plane, stored as blocks between the distinct
+// rectangle edges seen so far instead of one cell per light. Executing a
+// command costs as many blocks as the rectangle spans, not its area. Edges
+// `new` didn't see are inserted on first use, which moves every block after
+// them, so commands known up front should be passed to `new`.
+pub struct CompressedLights<R: LightRule> {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    cells: Vec<R::Cell>,
+    rule: R,
+}
+
+impl<R: LightRule> CompressedLights<R> {
+    pub fn new(rule: R, commands: &[Command]) -> Self {
+        let mut xs: Vec<i64> = Vec::new();
+        let mut ys: Vec<i64> = Vec::new();
+
+        for command in commands.iter() {
+            for area in command.shape.rects() {
+                xs.extend([area.start.x as i64, area.end.x as i64 + 1]);
+                ys.extend([area.start.y as i64, area.end.y as i64 + 1]);
+            }
+        }
+
+        xs.sort();
+        xs.dedup();
+        ys.sort();
+        ys.dedup();
+
+        let cells =
+            vec![R::Cell::default(); xs.len().saturating_sub(1) * ys.len().saturating_sub(1)];
+
+        Self {
+            xs,
+            ys,
+            cells,
+            rule,
+        }
+    }
+
+    fn columns(&self) -> usize {
+        self.xs.len().saturating_sub(1)
+    }
+
+    fn rows(&self) -> usize {
+        self.ys.len().saturating_sub(1)
+    }
+
+    // Maps a block index after inserting a boundary at `index` to the block
+    // it was split from, or `None` for a fresh block outside the old range.
+    fn source_block(index: usize, boundaries: usize, block: usize) -> Option<usize> {
+        if index == 0 {
+            block.checked_sub(1)
+        } else if index == boundaries {
+            (block + 1 < boundaries).then_some(block)
+        } else if block < index {
+            Some(block)
+        } else {
+            Some(block - 1)
+        }
+    }
+
+    fn ensure_x(&mut self, x: i64) -> usize {
+        let index = match self.xs.binary_search(&x) {
+            Ok(index) => return index,
+            Err(index) => index,
+        };
+
+        let boundaries = self.xs.len();
+        let (columns, rows) = (self.columns(), self.rows());
+        self.xs.insert(index, x);
+        let new_columns = self.columns();
+        if new_columns == columns {
+            return index;
+        }
+
+        // Widens every row in place, starting from the back so no block is
+        // overwritten before it has been moved.
+        self.cells.resize(rows * new_columns, R::Cell::default());
+        for row in (0..rows).rev() {
+            for column in (0..new_columns).rev() {
+                let value = Self::source_block(index, boundaries, column)
+                    .map_or_else(R::Cell::default, |source| {
+                        self.cells[row * columns + source].clone()
+                    });
+                self.cells[row * new_columns + column] = value;
+            }
+        }
+
+        index
+    }
+
+    fn ensure_y(&mut self, y: i64) -> usize {
+        let index = match self.ys.binary_search(&y) {
+            Ok(index) => return index,
+            Err(index) => index,
+        };
+
+        let boundaries = self.ys.len();
+        let (columns, rows) = (self.columns(), self.rows());
+        self.ys.insert(index, y);
+        if self.rows() == rows {
+            return index;
+        }
+
+        // Only one row is new: a copy of the row it was split from, or an
+        // empty one past either end.
+        let row = index.saturating_sub(1);
+        let values = match Self::source_block(index, boundaries, row) {
+            Some(source) => self.cells[source * columns..(source + 1) * columns].to_vec(),
+            None => vec![R::Cell::default(); columns],
+        };
+        self.cells.splice(row * columns..row * columns, values);
+
+        index
+    }
+
+    pub fn exec(&mut self, command: &Command) {
+        for area in command.shape.rects() {
+            self.exec_rect(&area, &command.operation);
+        }
+    }
+
+    fn exec_rect(&mut self, area: &Rect, operation: &CommandOperation) {
+        let column_start = self.ensure_x(area.start.x as i64);
+        let column_end = self.ensure_x(area.end.x as i64 + 1);
+        let row_start = self.ensure_y(area.start.y as i64);
+        let row_end = self.ensure_y(area.end.y as i64 + 1);
+
+        let columns = self.columns();
+        for row in row_start..row_end {
+            for light in
+                self.cells[row * columns + column_start..row * columns + column_end].iter_mut()
+            {
+                self.rule.apply(operation, light);
+            }
+        }
+    }
+
+    // Up to 2^32 by 2^32 lights for a block spanning the whole i32 plane,
+    // which doesn't fit in a u64.
+    fn block_area(&self, index: usize) -> u128 {
+        let (row, column) = (index / self.columns(), index % self.columns());
+        let width = self.xs[column + 1] - self.xs[column];
+        let height = self.ys[row + 1] - self.ys[row];
+        width as u128 * height as u128
+    }
+}
+
+impl<R: LightRule<Cell = u32>> CompressedLights<R> {
+    pub fn lit_count(&self) -> u128 {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, light)| **light > 0)
+            .map(|(index, _)| self.block_area(index))
+            .sum()
+    }
+
+    pub fn total_brightness(&self) -> u128 {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(index, light)| *light as u128 * self.block_area(index))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_rule::{BinaryRule, BrightnessRule};
+    use crate::lights::Lights;
+
+    // Small deterministic generator so the comparison covers more than a
+    // handful of hand-written rectangles.
+    fn random_commands(count: usize, seed: u64) -> Vec<Command> {
+        let mut state = seed;
+        let mut next = |max: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % max
+        };
+
+        (0..count)
+            .map(|_| {
+                let operation = ["turn on", "turn off", "toggle"][next(3) as usize];
+                let line = format!(
+                    "{} {},{} through {},{}",
+                    operation,
+                    next(1000),
+                    next(1000),
+                    next(1000),
+                    next(1000)
+                );
+                Command::new(&line).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_dense_lights() {
+        let commands = random_commands(60, 2015);
+
+        let mut lights = Lights::new(BinaryRule);
+        let mut lights_pt2 = Lights::new(BrightnessRule);
+        let mut compressed = CompressedLights::new(BinaryRule, &commands);
+        let mut compressed_pt2 = CompressedLights::new(BrightnessRule, &commands);
+        // Learns every edge while executing instead of up front.
+        let mut lazy_pt2 = CompressedLights::new(BrightnessRule, &[]);
+
+        for command in commands.iter() {
+            lights.exec(command);
+            lights_pt2.exec(command);
+            compressed.exec(command);
+            compressed_pt2.exec(command);
+            lazy_pt2.exec(command);
+        }
+
+        let lit = lights
+            .grid
+            .cells()
+            .iter()
+            .filter(|&x| matches!(x, 1))
+            .count() as u128;
+        let brightness: u128 = lights_pt2.grid.cells().iter().map(|&x| x as u128).sum();
+
+        assert_eq!(compressed.lit_count(), lit);
+        assert_eq!(compressed_pt2.total_brightness(), brightness);
+        assert_eq!(lazy_pt2.total_brightness(), brightness);
+    }
+
+    #[test]
+    fn test_matches_dense_lights_with_shapes() {
+        let commands: Vec<Command> = [
+            "set 7 circle at 500,500 radius 300",
+            "dim 3 200,200 through 700,650",
+            "invert circle at 100,900 radius 80",
+            "toggle 0,0 through 999,10",
+            "set 2 circle at 960,50 radius 39",
+        ]
+        .iter()
+        .map(|line| Command::new(line).unwrap())
+        .collect();
+
+        let mut lights = Lights::new(BrightnessRule);
+        let mut compressed = CompressedLights::new(BrightnessRule, &[]);
+        for command in commands.iter() {
+            lights.exec(command);
+            compressed.exec(command);
+        }
+
+        let brightness: u128 = lights.grid.cells().iter().map(|&x| x as u128).sum();
+        assert_eq!(compressed.total_brightness(), brightness);
+
+        // Rows are unbounded on the plane but clipped by the dense grid.
+        compressed.exec(&Command::new("turn on row -5").unwrap());
+        assert_eq!(compressed.total_brightness(), brightness + (1 << 32));
+    }
+
+    #[test]
+    fn test_unknown_and_negative_coordinates() {
+        let mut compressed = CompressedLights::new(BinaryRule, &[]);
+
+        compressed.exec(&Command::new("turn on -1000000,-5 through 999999,4").unwrap());
+        assert_eq!(compressed.lit_count(), 2_000_000 * 10);
+
+        compressed.exec(&Command::new("toggle 0,0 through 9,9").unwrap());
+        // Rows 0 to 4 were on and turn off, rows 5 to 9 were off and turn on,
+        // 50 cells each way.
+        assert_eq!(compressed.lit_count(), 20_000_000);
+
+        compressed.exec(&Command::new("toggle 2000000000,0 through 2000000001,0").unwrap());
+        assert_eq!(compressed.lit_count(), 2_000_000 * 10 + 2);
+
+        let mut compressed_pt2 = CompressedLights::new(BrightnessRule, &[]);
+        compressed_pt2.exec(&Command::new("toggle -3,-3 through -1,-1").unwrap());
+        compressed_pt2.exec(&Command::new("turn off -2,-2 through 5,5").unwrap());
+        assert_eq!(compressed_pt2.total_brightness(), 9 * 2 - 4);
+    }
+
+    #[test]
+    fn test_whole_plane() {
+        let mut compressed = CompressedLights::new(BrightnessRule, &[]);
+        compressed.exec(
+            &Command::new("turn on -2147483648,-2147483648 through 2147483647,2147483647").unwrap(),
+        );
+        compressed.exec(&Command::new("toggle 0,0 through 0,0").unwrap());
+
+        assert_eq!(compressed.total_brightness(), (1 << 64) + 2);
+        assert_eq!(compressed.lit_count(), 1 << 64);
+    }
+
+    #[test]
+    fn test_huge_circle() {
+        let command = Command::new("turn on circle at 0,0 radius 4000000000").unwrap();
+        let mut compressed = CompressedLights::new(BinaryRule, &[]);
+        compressed.exec(&command);
+        assert_eq!(compressed.lit_count(), 1 << 64);
+
+        let mut compressed = CompressedLights::new(BrightnessRule, std::slice::from_ref(&command));
+        compressed.exec(&command);
+        assert_eq!(compressed.total_brightness(), 1 << 64);
+    }
+}
@@ -0,0 +1,326 @@
+use utils::{grid::Grid, rect::Rect};
+
+use crate::command::Command;
+use crate::light_rule::LightRule;
+use crate::lights::Lights;
+
+// Previous values of a run of neighbouring lights, starting at `start` in
+// the grid. Commands change whole rectangles, so runs keep the indices out
+// of the record for all but the first light of every row.
+#[derive(Debug)]
+struct ChangedRun<C> {
+    start: usize,
+    before: Vec<C>,
+}
+
+#[derive(Debug)]
+enum Undo<C> {
+    // The command is self-inverse, running it again undoes it.
+    Replay,
+    Restore(Vec<ChangedRun<C>>),
+}
+
+#[derive(Debug)]
+struct Step<C> {
+    command: Command,
+    changed: usize,
+    undo: Undo<C>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SnapshotDiff {
+    pub changed: usize,
+    pub bounds: Option<Rect>,
+}
+
+pub fn diff_snapshots<C: PartialEq>(before: &Grid<C>, after: &Grid<C>) -> SnapshotDiff {
+    assert_eq!(
+        (before.width(), before.height()),
+        (after.width(), after.height()),
+        "Snapshots of different sizes cannot be compared"
+    );
+
+    let mut changed = 0;
+    let mut bounds: Option<Rect> = None;
+
+    for ((pos, a), b) in before.iter().zip(after.cells().iter()) {
+        if a != b {
+            changed += 1;
+            let cell = Rect::new(pos.clone(), pos);
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union_bbox(&cell),
+                None => cell,
+            });
+        }
+    }
+
+    SnapshotDiff { changed, bounds }
+}
+
+// Lights together with the commands executed on them so far. Undoing puts
+// back the recorded values, or runs self-inverse commands like toggles again
+// so they cost no memory. Redoing runs the command again, which gives the
+// same result because rules only look at the light they change.
+pub struct History<R: LightRule> {
+    lights: Lights<R>,
+    steps: Vec<Step<R::Cell>>,
+    position: usize,
+}
+
+impl<R: LightRule> History<R>
+where
+    R::Cell: PartialEq,
+{
+    pub fn new(lights: Lights<R>) -> Self {
+        Self {
+            lights,
+            steps: Vec::new(),
+            position: 0,
+        }
+    }
+
+    pub fn lights(&self) -> &Lights<R> {
+        &self.lights
+    }
+
+    pub fn snapshot(&self) -> Grid<R::Cell> {
+        self.lights.grid.clone()
+    }
+
+    // Number of commands currently applied.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // Number of recorded commands, including undone ones that can be redone.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn command(&self, index: usize) -> Option<&Command> {
+        self.steps.get(index).map(|step| &step.command)
+    }
+
+    // Executes and records the command. Anything undone before is dropped,
+    // like in an editor.
+    pub fn exec(&mut self, command: Command) {
+        self.steps.truncate(self.position);
+
+        let replay = self.lights.rule().is_self_inverse(&command.operation);
+        let mut changed = 0;
+        let mut runs: Vec<ChangedRun<R::Cell>> = Vec::new();
+        self.lights.exec_inspect(&command, |index, before, after| {
+            if before == after {
+                return;
+            }
+            changed += 1;
+            if replay {
+                return;
+            }
+
+            match runs.last_mut() {
+                Some(run) if run.start + run.before.len() == index => {
+                    run.before.push(before.clone())
+                }
+                _ => runs.push(ChangedRun {
+                    start: index,
+                    before: vec![before.clone()],
+                }),
+            }
+        });
+
+        let undo = if replay {
+            Undo::Replay
+        } else {
+            Undo::Restore(runs)
+        };
+        self.steps.push(Step {
+            command,
+            changed,
+            undo,
+        });
+        self.position += 1;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.position.checked_sub(1).map(|index| &self.steps[index]) else {
+            return false;
+        };
+
+        match &step.undo {
+            Undo::Replay => self.lights.exec(&step.command),
+            Undo::Restore(runs) => {
+                let cells = self.lights.grid.cells_mut();
+                for run in runs.iter() {
+                    cells[run.start..run.start + run.before.len()].clone_from_slice(&run.before);
+                }
+            }
+        }
+
+        self.position -= 1;
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(step) = self.steps.get(self.position) else {
+            return false;
+        };
+
+        self.lights.exec(&step.command);
+        self.position += 1;
+        true
+    }
+
+    // Moves to the state right after the first `position` recorded commands.
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        if position > self.steps.len() {
+            return false;
+        }
+
+        while self.position > position {
+            self.undo();
+        }
+        while self.position < position {
+            self.redo();
+        }
+
+        true
+    }
+
+    // Number of lights whose value was changed by each recorded command.
+    pub fn changed_lights(&self, index: usize) -> Option<usize> {
+        self.steps.get(index).map(|step| step.changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_rule::{BinaryRule, BrightnessRule};
+    use utils::vector2::Vector2;
+
+    fn commands() -> Vec<Command> {
+        [
+            "turn on 0,0 through 9,9",
+            "toggle 5,5 through 14,14",
+            "turn on 0,0 through 4,4",
+            "dim 1 circle at 500,500 radius 3",
+            "set 4 row 999",
+        ]
+        .iter()
+        .map(|line| Command::new(line).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_history_undo_redo() {
+        let mut history = History::new(Lights::new(BrightnessRule));
+        let mut snapshots = vec![history.snapshot()];
+        for command in commands() {
+            history.exec(command);
+            snapshots.push(history.snapshot());
+        }
+
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.position(), 5);
+
+        for position in (0..5).rev() {
+            assert!(history.undo());
+            assert_eq!(history.position(), position);
+            assert_eq!(history.snapshot(), snapshots[position]);
+        }
+        assert!(!history.undo());
+
+        assert!(history.redo());
+        assert!(history.redo());
+        assert_eq!(history.snapshot(), snapshots[2]);
+
+        assert!(history.jump_to(5));
+        assert_eq!(history.snapshot(), snapshots[5]);
+        assert!(!history.redo());
+        assert!(history.jump_to(1));
+        assert_eq!(history.snapshot(), snapshots[1]);
+        assert!(!history.jump_to(6));
+        assert_eq!(history.position(), 1);
+
+        history.exec(Command::new("turn off 0,0 through 0,0").unwrap());
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history.command(1),
+            Some(&Command::new("turn off 0,0 through 0,0").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_history_replays_self_inverse_commands() {
+        let mut history = History::new(Lights::new(BinaryRule));
+        let mut snapshots = vec![history.snapshot()];
+        for command in commands() {
+            history.exec(command);
+            snapshots.push(history.snapshot());
+        }
+        history.exec(Command::new("invert circle at 3,3 radius 5").unwrap());
+        snapshots.push(history.snapshot());
+
+        assert!(matches!(history.steps[0].undo, Undo::Restore(_)));
+        assert!(matches!(history.steps[1].undo, Undo::Replay));
+        assert!(matches!(history.steps[5].undo, Undo::Replay));
+
+        for position in (0..6).rev() {
+            assert!(history.undo());
+            assert_eq!(history.snapshot(), snapshots[position]);
+        }
+        assert!(history.jump_to(6));
+        assert_eq!(history.snapshot(), snapshots[6]);
+
+        // Toggling adds brightness, so it has to be restored from values.
+        let mut history = History::new(Lights::new(BrightnessRule));
+        history.exec(Command::new("toggle 0,0 through 1,1").unwrap());
+        assert!(matches!(history.steps[0].undo, Undo::Restore(_)));
+    }
+
+    #[test]
+    fn test_history_records_changed_lights() {
+        let mut history = History::new(Lights::new(BinaryRule));
+        for command in commands() {
+            history.exec(command);
+        }
+
+        assert_eq!(history.changed_lights(0), Some(100));
+        assert_eq!(history.changed_lights(1), Some(100));
+        // Lights 0,0 through 4,4 are already on.
+        assert_eq!(history.changed_lights(2), Some(0));
+        assert_eq!(history.changed_lights(3), Some(0));
+        assert_eq!(history.changed_lights(4), Some(1000));
+        assert_eq!(history.changed_lights(5), None);
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let mut history = History::new(Lights::new(BinaryRule));
+        let before = history.snapshot();
+        for command in commands() {
+            history.exec(command);
+        }
+        history.jump_to(2);
+
+        let diff = diff_snapshots(&before, &history.snapshot());
+        assert_eq!(diff.changed, 100 + 100 - 2 * 25);
+        assert_eq!(
+            diff.bounds,
+            Some(Rect::new(Vector2::new(0, 0), Vector2::new(14, 14)))
+        );
+
+        assert_eq!(
+            diff_snapshots(&before, &before),
+            SnapshotDiff {
+                changed: 0,
+                bounds: None,
+            }
+        );
+    }
+}
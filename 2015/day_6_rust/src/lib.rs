@@ -0,0 +1,9 @@
+pub mod animation;
+pub mod color_map;
+pub mod command;
+pub mod compressed_lights;
+pub mod history;
+pub mod light_rule;
+pub mod lights;
+pub mod render;
+pub mod vector2_ext;
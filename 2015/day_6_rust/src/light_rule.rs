@@ -0,0 +1,139 @@
+use crate::command::CommandOperation;
+
+// Decides what a single command operation does to one light. The grid
+// engines only walk the affected cells and leave the semantics to the rule.
+pub trait LightRule {
+    type Cell: Clone + Default;
+
+    fn apply(&self, operation: &CommandOperation, light: &mut Self::Cell);
+
+    // Whether applying `operation` twice always gives back the original
+    // light, so running it again undoes it.
+    fn is_self_inverse(&self, _operation: &CommandOperation) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BinaryRule;
+
+impl LightRule for BinaryRule {
+    type Cell = u32;
+
+    fn apply(&self, operation: &CommandOperation, light: &mut u32) {
+        *light = match operation {
+            CommandOperation::TurnOn => 1,
+            CommandOperation::TurnOff => 0,
+            CommandOperation::Toggle | CommandOperation::Invert => {
+                if *light == 0 {
+                    1
+                } else {
+                    0
+                }
+            }
+            CommandOperation::Set(brightness) => (*brightness).min(1),
+            CommandOperation::Dim(amount) => {
+                if *amount > 0 {
+                    0
+                } else {
+                    *light
+                }
+            }
+        };
+    }
+
+    fn is_self_inverse(&self, operation: &CommandOperation) -> bool {
+        matches!(
+            operation,
+            CommandOperation::Toggle | CommandOperation::Invert
+        )
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BrightnessRule;
+
+impl LightRule for BrightnessRule {
+    type Cell = u32;
+
+    fn apply(&self, operation: &CommandOperation, light: &mut u32) {
+        *light = match operation {
+            CommandOperation::TurnOn => light.saturating_add(1),
+            CommandOperation::TurnOff => light.saturating_sub(1),
+            CommandOperation::Toggle => light.saturating_add(2),
+            CommandOperation::Set(brightness) => *brightness,
+            CommandOperation::Dim(amount) => light.saturating_sub(*amount),
+            // Brightness has no upper bound to invert against, so inverting
+            // only swaps unlit and lit lights.
+            CommandOperation::Invert => {
+                if *light == 0 {
+                    1
+                } else {
+                    0
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_rule() {
+        let mut light = 0;
+
+        BinaryRule.apply(&CommandOperation::Toggle, &mut light);
+        assert_eq!(light, 1);
+        BinaryRule.apply(&CommandOperation::TurnOn, &mut light);
+        assert_eq!(light, 1);
+        BinaryRule.apply(&CommandOperation::Toggle, &mut light);
+        assert_eq!(light, 0);
+        BinaryRule.apply(&CommandOperation::TurnOff, &mut light);
+        assert_eq!(light, 0);
+        BinaryRule.apply(&CommandOperation::Set(5), &mut light);
+        assert_eq!(light, 1);
+        BinaryRule.apply(&CommandOperation::Invert, &mut light);
+        assert_eq!(light, 0);
+        BinaryRule.apply(&CommandOperation::Invert, &mut light);
+        BinaryRule.apply(&CommandOperation::Dim(0), &mut light);
+        assert_eq!(light, 1);
+        BinaryRule.apply(&CommandOperation::Dim(1), &mut light);
+        assert_eq!(light, 0);
+    }
+
+    #[test]
+    fn test_brightness_rule() {
+        let mut light = 0;
+
+        BrightnessRule.apply(&CommandOperation::TurnOff, &mut light);
+        assert_eq!(light, 0);
+        BrightnessRule.apply(&CommandOperation::Toggle, &mut light);
+        assert_eq!(light, 2);
+        BrightnessRule.apply(&CommandOperation::TurnOn, &mut light);
+        assert_eq!(light, 3);
+        BrightnessRule.apply(&CommandOperation::TurnOff, &mut light);
+        assert_eq!(light, 2);
+        BrightnessRule.apply(&CommandOperation::Set(9), &mut light);
+        assert_eq!(light, 9);
+        BrightnessRule.apply(&CommandOperation::Dim(4), &mut light);
+        assert_eq!(light, 5);
+        BrightnessRule.apply(&CommandOperation::Dim(40), &mut light);
+        assert_eq!(light, 0);
+        BrightnessRule.apply(&CommandOperation::Invert, &mut light);
+        assert_eq!(light, 1);
+    }
+
+    #[test]
+    fn test_brightness_rule_saturates() {
+        let mut light = u32::MAX - 1;
+
+        BrightnessRule.apply(&CommandOperation::Toggle, &mut light);
+        assert_eq!(light, u32::MAX);
+        BrightnessRule.apply(&CommandOperation::TurnOn, &mut light);
+        assert_eq!(light, u32::MAX);
+        BrightnessRule.apply(&CommandOperation::Toggle, &mut light);
+        assert_eq!(light, u32::MAX);
+    }
+}
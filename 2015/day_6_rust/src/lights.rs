@@ -1,57 +1,36 @@
-use utils::vector2::Vector2;
+use utils::{grid::Grid, rect::Rect, vector2::Vector2};
 
 use crate::command::*;
+use crate::light_rule::LightRule;
 
-pub struct Lights {
-    pub grid: Vec<u32>,
+pub struct Lights<R: LightRule> {
+    pub grid: Grid<R::Cell>,
     pub size: usize,
+    rule: R,
 }
 
-impl Lights {
-    pub fn new() -> Self {
+impl<R: LightRule + Default> Default for Lights<R> {
+    fn default() -> Self {
+        Self::new(R::default())
+    }
+}
+
+impl<R: LightRule> Lights<R> {
+    pub fn new(rule: R) -> Self {
         let size = 1000_usize;
         Self {
             size,
-            grid: vec![0; size * size],
+            grid: Grid::new(size, size, R::Cell::default()),
+            rule,
         }
     }
 
-    fn is_pos_out_of_bounds(&self, pos: &Vector2) -> bool {
-        pos.x < 0 || pos.y < 0 || pos.x >= self.size as i32 || pos.y >= self.size as i32
-    }
-
-    pub fn vector_to_index(&self, pos: &Vector2) -> usize {
-        (pos.y * self.size as i32 + pos.x) as usize
-    }
-
-    pub fn index_to_vector(&self, index: &usize) -> Vector2 {
-        let x = index % self.size;
-        let y = index / self.size;
-        Vector2::new(x as i32, y as i32)
+    fn get(&self, pos: &Vector2) -> Option<&R::Cell> {
+        self.grid.get(pos)
     }
 
-    fn get(&self, pos: &Vector2) -> Option<&u32> {
-        if self.is_pos_out_of_bounds(&pos) {
-            return None;
-        }
-
-        let index = self.vector_to_index(&pos);
-
-        if let Some(light) = self.grid.get(index) {
-            Some(light)
-        } else {
-            None
-        }
-    }
-
-    fn set(&mut self, pos: &Vector2, state: u32) -> bool {
-        if self.is_pos_out_of_bounds(&pos) {
-            return false;
-        }
-
-        let index = self.vector_to_index(&pos);
-
-        if let Some(light) = self.grid.get_mut(index) {
+    fn set(&mut self, pos: &Vector2, state: R::Cell) -> bool {
+        if let Some(light) = self.grid.get_mut(pos) {
             *light = state;
             return true;
         }
@@ -59,53 +38,34 @@ impl Lights {
         false
     }
 
-    pub fn exec(&mut self, command: &Command) -> () {
-        let mut x_asix_range: Vec<usize> = vec![command.start.x as usize, command.end.x as usize];
-        let mut y_asix_range: Vec<usize> = vec![command.start.y as usize, command.end.y as usize];
-        x_asix_range.sort();
-        y_asix_range.sort();
-
-        let x_start = *x_asix_range.get(0).unwrap_or(&0);
-        let x_end = *x_asix_range.get(1).unwrap_or(&0);
-        let y_start = *y_asix_range.get(0).unwrap_or(&0);
-        let y_end = *y_asix_range.get(1).unwrap_or(&0);
-
-        for x in x_start..=x_end {
-            for y in y_start..=y_end {
-                let pos = Vector2::new(x as i32, y as i32);
-
-                if let Some(light) = self.get(&pos) {
-                    match command.operation {
-                        CommandOperation::TurnOn => self.set(&pos, 1),
-                        CommandOperation::TurnOff => self.set(&pos, 0),
-                        CommandOperation::Toggle => self.set(&pos, if *light == 0 { 1 } else { 0 }),
-                    };
-                }
-            }
-        }
+    pub fn exec(&mut self, command: &Command) {
+        self.exec_inspect(command, |_, _, _| {});
     }
 
-    pub fn exec_pt2(&mut self, command: &Command) -> () {
-        let mut x_asix_range: Vec<usize> = vec![command.start.x as usize, command.end.x as usize];
-        let mut y_asix_range: Vec<usize> = vec![command.start.y as usize, command.end.y as usize];
-        x_asix_range.sort();
-        y_asix_range.sort();
-
-        let x_start = *x_asix_range.get(0).unwrap_or(&0);
-        let x_end = *x_asix_range.get(1).unwrap_or(&0);
-        let y_start = *y_asix_range.get(0).unwrap_or(&0);
-        let y_end = *y_asix_range.get(1).unwrap_or(&0);
+    pub(crate) fn rule(&self) -> &R {
+        &self.rule
+    }
 
-        for x in x_start..=x_end {
-            for y in y_start..=y_end {
-                let pos = Vector2::new(x as i32, y as i32);
+    // Executes the command and hands every touched light to `inspect` as its
+    // grid index and its values before and after the rule was applied.
+    pub(crate) fn exec_inspect<F>(&mut self, command: &Command, mut inspect: F)
+    where
+        F: FnMut(usize, &R::Cell, &R::Cell),
+    {
+        let bounds = Rect::new(
+            Vector2::new(0, 0),
+            Vector2::new(self.size as i32 - 1, self.size as i32 - 1),
+        );
 
-                if let Some(light) = self.get(&pos) {
-                    match command.operation {
-                        CommandOperation::TurnOn => self.set(&pos, *light + 1),
-                        CommandOperation::TurnOff => self.set(&pos, light.saturating_sub(1)),
-                        CommandOperation::Toggle => self.set(&pos, *light + 2),
-                    };
+        for area in command.shape.rects_within(&bounds) {
+            for pos in area.cells() {
+                if let Some(before) = self.get(&pos) {
+                    let mut light = before.clone();
+                    self.rule.apply(&command.operation, &mut light);
+                    if let Some(index) = self.grid.index_of(&pos) {
+                        inspect(index, before, &light);
+                    }
+                    self.set(&pos, light);
                 }
             }
         }
@@ -115,12 +75,13 @@ impl Lights {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::light_rule::{BinaryRule, BrightnessRule};
 
     #[test]
     fn test_lights_creation() {
-        let lights = Lights::new();
+        let lights = Lights::new(BinaryRule);
 
-        assert_eq!(lights.grid.len(), lights.size * lights.size);
+        assert_eq!(lights.grid.cells().len(), lights.size * lights.size);
 
         assert!(
             matches!(lights.get(&Vector2::new(0, 0)), Some(0)),
@@ -131,37 +92,37 @@ mod tests {
             "should get light at 999,999"
         );
         assert!(
-            matches!(lights.get(&Vector2::new(1000, 1000)), None),
+            lights.get(&Vector2::new(1000, 1000)).is_none(),
             "should get None at 1000,1000, out of bounds"
         );
         assert!(
-            matches!(lights.get(&Vector2::new(-1, 0)), None),
+            lights.get(&Vector2::new(-1, 0)).is_none(),
             "should get None at -1,0, out of bounds"
         );
         assert!(
-            matches!(lights.get(&Vector2::new(0, -1)), None),
+            lights.get(&Vector2::new(0, -1)).is_none(),
             "should get None at 0,-1 out of bounds"
         );
     }
 
     #[test]
     fn test_lights_set() {
-        let mut lights = Lights::new();
+        let mut lights = Lights::new(BinaryRule);
 
         assert!(matches!(lights.get(&Vector2::new(0, 0)), Some(0)));
 
-        assert_eq!(lights.set(&Vector2::new(0, 0), 1), true);
+        assert!(lights.set(&Vector2::new(0, 0), 1));
         assert!(matches!(lights.get(&Vector2::new(0, 0)), Some(1)));
 
-        assert_eq!(lights.set(&Vector2::new(999, 999), 1), true);
+        assert!(lights.set(&Vector2::new(999, 999), 1));
         assert!(matches!(lights.get(&Vector2::new(999, 999)), Some(1)));
 
-        assert_eq!(lights.set(&Vector2::new(1000, 1000), 1), false);
+        assert!(!lights.set(&Vector2::new(1000, 1000), 1));
     }
 
     #[test]
     fn test_lights_exec() {
-        let mut lights = Lights::new();
+        let mut lights = Lights::new(BinaryRule);
 
         lights.exec(&Command::new("turn on 0,0 through 999,999").unwrap());
         assert!(matches!(lights.get(&Vector2::new(500, 500)), Some(1)));
@@ -173,4 +134,106 @@ mod tests {
         lights.exec(&Command::new("turn off 499,499 through 500,500").unwrap());
         assert!(matches!(lights.get(&Vector2::new(500, 500)), Some(0)));
     }
+
+    #[test]
+    fn test_lights_covered_area() {
+        let mut lights = Lights::new(BinaryRule);
+        let commands: Vec<Command> = [
+            "turn on 10,10 through 300,200",
+            "turn on 250,150 through 120,900",
+            "turn on 0,999 through 999,990",
+            "turn on 200,100 through 200,100",
+        ]
+        .iter()
+        .map(|line| Command::new(line).unwrap())
+        .collect();
+
+        for command in commands.iter() {
+            lights.exec(command);
+        }
+        let areas: Vec<Rect> = commands.iter().flat_map(|c| c.shape.rects()).collect();
+
+        assert_eq!(
+            Rect::union_area(&areas),
+            lights.grid.cells().iter().sum::<u32>() as u128
+        );
+    }
+
+    #[test]
+    fn test_lights_exec_brightness() {
+        let mut lights = Lights::new(BrightnessRule);
+
+        lights.exec(&Command::new("turn on 0,0 through 0,0").unwrap());
+        assert!(matches!(lights.get(&Vector2::new(0, 0)), Some(1)));
+
+        lights.exec(&Command::new("toggle 0,0 through 999,999").unwrap());
+        assert_eq!(lights.grid.cells().iter().sum::<u32>(), 2000001);
+
+        lights.exec(&Command::new("turn off 0,0 through 1,0").unwrap());
+        lights.exec(&Command::new("turn off 0,0 through 1,0").unwrap());
+        lights.exec(&Command::new("turn off 0,0 through 1,0").unwrap());
+        assert!(matches!(lights.get(&Vector2::new(0, 0)), Some(0)));
+        assert!(matches!(lights.get(&Vector2::new(1, 0)), Some(0)));
+    }
+
+    struct CappedBrightnessRule {
+        max: u8,
+    }
+
+    impl LightRule for CappedBrightnessRule {
+        type Cell = u8;
+
+        fn apply(&self, operation: &CommandOperation, light: &mut u8) {
+            *light = match operation {
+                CommandOperation::TurnOn => light.saturating_add(1),
+                CommandOperation::TurnOff => light.saturating_sub(1),
+                CommandOperation::Toggle => light.saturating_add(2),
+                CommandOperation::Set(brightness) => (*brightness).min(u8::MAX as u32) as u8,
+                CommandOperation::Dim(amount) => {
+                    light.saturating_sub((*amount).min(u8::MAX as u32) as u8)
+                }
+                CommandOperation::Invert => self.max.saturating_sub(*light),
+            }
+            .min(self.max);
+        }
+    }
+
+    #[derive(Clone, Default, Debug, PartialEq)]
+    struct Rgb(u8, u8, u8);
+
+    // Every operation drives its own colour channel, so the final colour
+    // records which kinds of commands touched the light.
+    struct ColourMixRule;
+
+    impl LightRule for ColourMixRule {
+        type Cell = Rgb;
+
+        fn apply(&self, operation: &CommandOperation, light: &mut Rgb) {
+            match operation {
+                CommandOperation::TurnOn => light.0 = light.0.saturating_add(64),
+                CommandOperation::TurnOff => light.1 = light.1.saturating_add(64),
+                CommandOperation::Toggle => light.2 = !light.2,
+                CommandOperation::Invert => *light = Rgb(!light.0, !light.1, !light.2),
+                CommandOperation::Set(_) | CommandOperation::Dim(_) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_lights_custom_rules() {
+        let mut capped = Lights::new(CappedBrightnessRule { max: 3 });
+        for _ in 0..3 {
+            capped.exec(&Command::new("toggle 10,10 through 11,11").unwrap());
+        }
+        assert!(matches!(capped.get(&Vector2::new(10, 10)), Some(3)));
+        assert!(matches!(capped.get(&Vector2::new(12, 12)), Some(0)));
+
+        let mut colours = Lights::new(ColourMixRule);
+        colours.exec(&Command::new("turn on 0,0 through 1,0").unwrap());
+        colours.exec(&Command::new("toggle 1,0 through 2,0").unwrap());
+        colours.exec(&Command::new("turn off 2,0 through 2,0").unwrap());
+        assert_eq!(colours.get(&Vector2::new(0, 0)), Some(&Rgb(64, 0, 0)));
+        assert_eq!(colours.get(&Vector2::new(1, 0)), Some(&Rgb(64, 0, 255)));
+        assert_eq!(colours.get(&Vector2::new(2, 0)), Some(&Rgb(0, 64, 255)));
+    }
 }
@@ -1,70 +1,172 @@
-use image::{Rgb, RgbImage};
 use std::{
+    env,
     fs::File,
     io::{BufRead, BufReader, Error},
 };
 
-use command::Command;
-use lights::Lights;
-
-mod command;
-mod lights;
-mod vector2_ext;
+use day_6_rust::{
+    animation::{save_animation, AnimationFormat, AnimationOptions},
+    color_map::{ColorMap, Scale},
+    command::Command,
+    history::{diff_snapshots, History},
+    light_rule::{BinaryRule, BrightnessRule, LightRule},
+    lights::Lights,
+    render::{render_lights, Legend, RenderOptions},
+};
 
-fn scale_brightness(value: u32, max_brightness: u32) -> u8 {
-    let scaled = (value as f32 / max_brightness as f32 * 255.0).round() as u8;
-    scaled
+fn lights_to_image<R: LightRule<Cell = u32>>(
+    lights: &Lights<R>,
+    options: &RenderOptions,
+    filename: &str,
+) {
+    render_lights(lights, options).save(filename).unwrap();
 }
 
-fn lights_to_image(lights: &Lights, filename: &str) {
-    let mut img: RgbImage = RgbImage::new(lights.size as u32, lights.size as u32);
-
-    let max_brightness = *lights.grid.iter().max().unwrap();
-
-    for (index, light) in lights.grid.iter().enumerate() {
-        let pos = lights.index_to_vector(&index);
+// Reads `--colors grey|heat|viridis|inferno|binary[:RRGGBB:RRGGBB]`,
+// `--scale linear|log` and `--legend none|gradient|histogram` for the images,
+// and `--animate gif|apng` with optional `--every`, `--fps` and `--downscale`
+// for the animations. Colours and scale apply to both. `--rewind k` also
+// renders part 1 as it was after the first k commands.
+struct Options {
+    render: RenderOptions,
+    animation: Option<AnimationOptions>,
+    rewind: Option<usize>,
+}
 
-        let brightness = scale_brightness(*light, max_brightness);
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut render = RenderOptions::default();
+    let mut animation = AnimationOptions::default();
+    let mut animate = false;
+    let mut rewind = None;
 
-        img.put_pixel(
-            pos.x as u32,
-            pos.y as u32,
-            Rgb([brightness, brightness, brightness]),
-        );
+    for pair in args.chunks(2) {
+        let (flag, value) = (pair[0].as_str(), pair.get(1).map(String::as_str));
+        match (flag, value) {
+            ("--colors", Some(name)) => render.color_map = ColorMap::from_name(name)?,
+            ("--scale", Some("linear")) => render.scale = Scale::Linear,
+            ("--scale", Some("log")) => render.scale = Scale::Log,
+            ("--legend", Some("none")) => render.legend = Legend::None,
+            ("--legend", Some("gradient")) => render.legend = Legend::Gradient,
+            ("--legend", Some("histogram")) => render.legend = Legend::Histogram,
+            ("--animate", Some("gif")) => animate = true,
+            ("--animate", Some("apng")) => {
+                animate = true;
+                animation.format = AnimationFormat::Apng;
+            }
+            ("--every", Some(value)) => animation.frame_every = value.parse().ok()?,
+            ("--fps", Some(value)) => animation.frame_rate = value.parse().ok()?,
+            ("--downscale", Some(value)) => animation.downscale = value.parse().ok()?,
+            ("--rewind", Some(value)) => rewind = Some(value.parse().ok()?),
+            _ => {
+                println!("Unknown option {}", flag);
+                return None;
+            }
+        }
     }
 
-    img.save(filename).unwrap();
+    animation.color_map = render.color_map.clone();
+    animation.scale = render.scale;
+
+    Some(Options {
+        render,
+        animation: animate.then_some(animation),
+        rewind,
+    })
 }
 
 fn main() -> Result<(), Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(options) = parse_options(&args) else {
+        return Ok(());
+    };
+
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
 
     let commands: Vec<Command> = reader
         .lines()
-        .filter_map(Result::ok)
-        .map(|c| Command::new(&c))
-        .filter_map(Result::ok)
+        .map_while(Result::ok)
+        .enumerate()
+        .filter_map(|(index, line)| match Command::new(&line) {
+            Ok(command) => Some(command),
+            Err(error) => {
+                println!("Skipping line {}: {}", index + 1, error);
+                None
+            }
+        })
         .collect();
 
     // Part 1
-    let mut lights = Lights::new();
+    let mut lights = Lights::new(BinaryRule);
     for command in commands.iter() {
         lights.exec(command);
     }
-    let lights_lit = lights.grid.iter().filter(|&x| matches!(x, 1)).count();
+    let lights_lit = lights
+        .grid
+        .cells()
+        .iter()
+        .filter(|&x| matches!(x, 1))
+        .count();
     println!("Lights lit {}", lights_lit);
 
     // Part 2
-    let mut lights_pt2 = Lights::new();
+    let mut lights_pt2 = Lights::new(BrightnessRule);
     for command in commands.iter() {
-        lights_pt2.exec_pt2(command);
+        lights_pt2.exec(command);
     }
-    let total_brightness: u32 = lights_pt2.grid.iter().sum();
+    let total_brightness: u32 = lights_pt2.grid.cells().iter().sum();
     println!("Total brightness {}", total_brightness);
 
-    lights_to_image(&lights, "part1.png");
-    lights_to_image(&lights_pt2, "part2.png");
+    lights_to_image(&lights, &options.render, "part1.png");
+    lights_to_image(&lights_pt2, &options.render, "part2.png");
+
+    // Part 1 again, this time keeping what every command changed so it can
+    // be rewound. Only done when asked since that history takes memory.
+    if let Some(rewind) = options.rewind {
+        let mut history = History::new(Lights::new(BinaryRule));
+        for command in commands.iter() {
+            history.exec(command.clone());
+        }
+        let last = history.snapshot();
+        if history.jump_to(rewind) {
+            let diff = diff_snapshots(&history.snapshot(), &last);
+            println!(
+                "After command {} of {}, {} lights differ from the end result within {:?}",
+                rewind,
+                history.len(),
+                diff.changed,
+                diff.bounds
+            );
+            lights_to_image(
+                history.lights(),
+                &options.render,
+                &format!("part1_step_{}.png", rewind),
+            );
+        } else {
+            println!("There are only {} commands", history.len());
+        }
+    }
+
+    if let Some(options) = options.animation {
+        let extension = match options.format {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        };
+        save_animation(
+            BinaryRule,
+            &commands,
+            &options,
+            &format!("part1_animation.{}", extension),
+        )
+        .unwrap();
+        save_animation(
+            BrightnessRule,
+            &commands,
+            &options,
+            &format!("part2_animation.{}", extension),
+        )
+        .unwrap();
+    }
 
     Ok(())
 }
@@ -0,0 +1,195 @@
+use image::{Rgb, RgbImage};
+
+use crate::color_map::{ColorMap, Scale};
+use crate::light_rule::LightRule;
+use crate::lights::Lights;
+
+const GRADIENT_WIDTH: u32 = 16;
+const HISTOGRAM_WIDTH: u32 = 64;
+const STRIP_BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+
+// Strip drawn on the right edge of the image. Its rows run from the
+// brightest light at the top to no light at the bottom, and the histogram
+// adds a bar per row sized by how many lights fall into it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Legend {
+    None,
+    Gradient,
+    Histogram,
+}
+
+impl Legend {
+    fn width(&self) -> u32 {
+        match self {
+            Legend::None => 0,
+            Legend::Gradient => GRADIENT_WIDTH,
+            Legend::Histogram => GRADIENT_WIDTH + HISTOGRAM_WIDTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub color_map: ColorMap,
+    pub scale: Scale,
+    pub legend: Legend,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            color_map: ColorMap::Greyscale,
+            scale: Scale::Linear,
+            legend: Legend::None,
+        }
+    }
+}
+
+fn legend_row(value: f32, height: u32) -> u32 {
+    let row = ((1.0 - value.clamp(0.0, 1.0)) * (height - 1) as f32).round() as u32;
+    row.min(height - 1)
+}
+
+fn draw_legend(img: &mut RgbImage, values: &[f32], options: &RenderOptions, x_start: u32) {
+    let height = img.height();
+    if height == 0 || options.legend == Legend::None {
+        return;
+    }
+
+    for y in 0..height {
+        let value = 1.0 - y as f32 / (height - 1).max(1) as f32;
+        let color = options.color_map.color(value);
+        for x in x_start..x_start + GRADIENT_WIDTH {
+            img.put_pixel(x, y, color);
+        }
+    }
+
+    if options.legend != Legend::Histogram {
+        return;
+    }
+
+    let mut counts: Vec<u64> = vec![0; height as usize];
+    for value in values.iter() {
+        counts[legend_row(*value, height) as usize] += 1;
+    }
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+
+    let bars_start = x_start + GRADIENT_WIDTH;
+    for (y, count) in counts.iter().enumerate() {
+        // Bars are log scaled, otherwise the unlit lights usually dwarf
+        // everything else.
+        let length = if *count == 0 {
+            0
+        } else {
+            ((*count as f32).ln_1p() / (max_count as f32).ln_1p() * HISTOGRAM_WIDTH as f32).ceil()
+                as u32
+        };
+        let bar_color = options
+            .color_map
+            .color(1.0 - y as f32 / (height - 1).max(1) as f32);
+
+        for x in 0..HISTOGRAM_WIDTH {
+            let color = if x < length {
+                bar_color
+            } else {
+                STRIP_BACKGROUND
+            };
+            img.put_pixel(bars_start + x, y as u32, color);
+        }
+    }
+}
+
+pub fn render_lights<R: LightRule<Cell = u32>>(
+    lights: &Lights<R>,
+    options: &RenderOptions,
+) -> RgbImage {
+    let size = lights.size as u32;
+    let mut img: RgbImage = RgbImage::new(size + options.legend.width(), size);
+
+    let max_brightness = *lights.grid.cells().iter().max().unwrap_or(&0) as f32;
+    let values: Vec<f32> = lights
+        .grid
+        .cells()
+        .iter()
+        .map(|light| options.scale.normalize(*light as f32, max_brightness))
+        .collect();
+
+    for (index, value) in values.iter().enumerate() {
+        let pos = lights.grid.position_of(index);
+
+        img.put_pixel(pos.x as u32, pos.y as u32, options.color_map.color(*value));
+    }
+
+    draw_legend(&mut img, &values, options, size);
+
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+    use crate::light_rule::{BinaryRule, BrightnessRule};
+
+    #[test]
+    fn test_render_empty_and_flat_grid() {
+        let mut lights = Lights::new(BinaryRule);
+
+        let img = render_lights(&lights, &RenderOptions::default());
+        assert_eq!(img.dimensions(), (1000, 1000));
+        assert!(img.pixels().all(|pixel| *pixel == Rgb([0, 0, 0])));
+
+        lights.exec(&Command::new("turn on 0,0 through 999,999").unwrap());
+        let options = RenderOptions {
+            color_map: ColorMap::Viridis,
+            scale: Scale::Log,
+            ..Default::default()
+        };
+        let img = render_lights(&lights, &options);
+        assert!(img.pixels().all(|pixel| *pixel == Rgb([253, 231, 37])));
+    }
+
+    #[test]
+    fn test_render_log_scale() {
+        let mut lights = Lights::new(BrightnessRule);
+        lights.exec(&Command::new("turn on 0,0 through 0,0").unwrap());
+        for _ in 0..7 {
+            lights.exec(&Command::new("toggle 1,0 through 1,0").unwrap());
+        }
+
+        let linear = render_lights(&lights, &RenderOptions::default());
+        assert_eq!(*linear.get_pixel(0, 0), Rgb([18, 18, 18]));
+
+        let options = RenderOptions {
+            scale: Scale::Log,
+            ..Default::default()
+        };
+        let log = render_lights(&lights, &options);
+        assert_eq!(*log.get_pixel(0, 0), Rgb([65, 65, 65]));
+        assert_eq!(*log.get_pixel(1, 0), Rgb([255, 255, 255]));
+    }
+
+    #[test]
+    fn test_render_legend() {
+        let mut lights = Lights::new(BinaryRule);
+        lights.exec(&Command::new("turn on 0,0 through 999,249").unwrap());
+
+        let options = RenderOptions {
+            color_map: ColorMap::Heat,
+            legend: Legend::Histogram,
+            ..Default::default()
+        };
+        let img = render_lights(&lights, &options);
+
+        assert_eq!(img.dimensions(), (1000 + 80, 1000));
+        assert_eq!(*img.get_pixel(1000, 0), Rgb([255, 255, 255]));
+        assert_eq!(*img.get_pixel(1015, 999), Rgb([0, 0, 0]));
+
+        // Three times as many unlit lights, so the bottom bar is the longest
+        // and the lit bar at the top is a little shorter on a log scale.
+        assert_eq!(*img.get_pixel(1079, 999), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(1070, 0), Rgb([255, 255, 255]));
+        assert_eq!(*img.get_pixel(1079, 0), STRIP_BACKGROUND);
+        assert_eq!(*img.get_pixel(1050, 500), STRIP_BACKGROUND);
+    }
+}
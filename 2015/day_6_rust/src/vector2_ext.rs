@@ -13,7 +13,7 @@ pub trait Vector2Ext {
 
 impl Vector2Ext for Vector2 {
     fn new_from_str(s: &str) -> Result<Self, Vector2CreationError> {
-        if s == "" {
+        if s.is_empty() {
             return Err(Vector2CreationError::EmptyValue);
         }
 
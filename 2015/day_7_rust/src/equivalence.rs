@@ -0,0 +1,445 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::wire_kit::{GateUtil, WiresKit};
+
+#[derive(Debug)]
+pub enum TestVectors {
+    Exhaustive,
+    Random { samples: usize, seed: u64 },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub inputs: Vec<u64>,
+    pub expected: Vec<u64>,
+    pub actual: Vec<Option<u64>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EquivalenceError {
+    // Exhaustive testing would need 2^bits test vectors.
+    TooManyInputBits { bits: u64 },
+    Mismatch(Mismatch),
+}
+
+// Largest number of input bits tested exhaustively.
+const MAX_EXHAUSTIVE_BITS: u64 = 24;
+
+impl Mismatch {
+    fn first_failing_output(&self) -> usize {
+        self.expected
+            .iter()
+            .zip(self.actual.iter())
+            .position(|(expected, actual)| Some(*expected) != *actual)
+            .unwrap_or(0)
+    }
+}
+
+// The circuit under test: which wires are driven with test values, which
+// are compared, and the function they are supposed to implement.
+pub struct Reference<'a, F>
+where
+    F: Fn(&[u64]) -> Vec<u64>,
+{
+    pub inputs: &'a [&'a str],
+    pub outputs: &'a [&'a str],
+    pub function: F,
+}
+
+impl WiresKit {
+    fn test_vectors(
+        &self,
+        input_count: usize,
+        vectors: &TestVectors,
+    ) -> Result<Vec<Vec<u64>>, EquivalenceError> {
+        let vectors = match vectors {
+            TestVectors::Exhaustive => {
+                let bits = (input_count as u64).saturating_mul(self.width() as u64);
+                if bits > MAX_EXHAUSTIVE_BITS {
+                    return Err(EquivalenceError::TooManyInputBits { bits });
+                }
+
+                (0..1_u64 << bits)
+                    .map(|combination| {
+                        (0..input_count)
+                            .map(|index| {
+                                (combination >> (index as u32 * self.width())) & self.mask()
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+            TestVectors::Random { samples, seed } => {
+                let mut rng = StdRng::seed_from_u64(*seed);
+
+                (0..*samples)
+                    .map(|_| {
+                        (0..input_count)
+                            .map(|_| rng.gen::<u64>() & self.mask())
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(vectors)
+    }
+
+    fn find_mismatch<F>(
+        &mut self,
+        reference: &Reference<F>,
+        vectors: &[Vec<u64>],
+    ) -> Option<Mismatch>
+    where
+        F: Fn(&[u64]) -> Vec<u64>,
+    {
+        self.reset_all_wires_expect_inputs();
+
+        for inputs in vectors.iter() {
+            for (wire_name, signal) in reference.inputs.iter().zip(inputs.iter()) {
+                self.override_wire(wire_name, *signal);
+            }
+
+            let expected: Vec<u64> = (reference.function)(inputs)
+                .into_iter()
+                .map(|signal| signal & self.mask())
+                .collect();
+            let actual: Vec<Option<u64>> = reference
+                .outputs
+                .iter()
+                .map(|wire_name| self.probe(wire_name).ok())
+                .collect();
+
+            let matches = expected
+                .iter()
+                .zip(actual.iter())
+                .all(|(expected, actual)| Some(*expected) == *actual);
+            if !matches {
+                return Some(Mismatch {
+                    inputs: inputs.to_vec(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        None
+    }
+
+    // The input wires are overridden with every test vector in turn, so all
+    // wire signals are put back once done.
+    pub fn check_equivalence<F>(
+        &mut self,
+        reference: &Reference<F>,
+        vectors: &TestVectors,
+    ) -> Result<(), EquivalenceError>
+    where
+        F: Fn(&[u64]) -> Vec<u64>,
+    {
+        let vectors = self.test_vectors(reference.inputs.len(), vectors)?;
+        let wires = self.wire.clone();
+
+        let mismatch = self.find_mismatch(reference, &vectors);
+        self.wire = wires;
+
+        match mismatch {
+            Some(mismatch) => Err(EquivalenceError::Mismatch(mismatch)),
+            None => Ok(()),
+        }
+    }
+
+    fn swap_outputs(&mut self, a: usize, b: usize) {
+        let (low, high) = (a.min(b), a.max(b));
+        let (left, right) = self.connections.split_at_mut(high);
+
+        std::mem::swap(left[low].get_output_mut(), right[0].get_output_mut());
+    }
+
+    // Finds the smallest set of gate output swaps, at most `max_swaps` of
+    // them, after which the circuit agrees with the reference on every test
+    // vector. The circuit is left unchanged.
+    pub fn find_output_swaps<F>(
+        &mut self,
+        reference: &Reference<F>,
+        vectors: &TestVectors,
+        max_swaps: usize,
+    ) -> Result<Option<Vec<(String, String)>>, EquivalenceError>
+    where
+        F: Fn(&[u64]) -> Vec<u64>,
+    {
+        let vectors = self.test_vectors(reference.inputs.len(), vectors)?;
+        let wires = self.wire.clone();
+        let mut swaps: Vec<(usize, usize)> = Vec::new();
+        let mut swapped: Vec<bool> = vec![false; self.connections.len()];
+
+        for depth in 0..=max_swaps {
+            if self.search_swaps(reference, &vectors, depth, &mut swaps, &mut swapped) {
+                let mut pairs: Vec<(String, String)> = swaps
+                    .iter()
+                    .map(|&(a, b)| {
+                        let mut pair = [
+                            self.connections[a].get_output_wire_name(),
+                            self.connections[b].get_output_wire_name(),
+                        ];
+                        pair.sort();
+                        let [first, second] = pair;
+                        (first, second)
+                    })
+                    .collect();
+
+                for &(a, b) in swaps.iter().rev() {
+                    self.swap_outputs(a, b);
+                }
+                self.wire = wires;
+
+                pairs.sort();
+                return Ok(Some(pairs));
+            }
+        }
+
+        self.wire = wires;
+        Ok(None)
+    }
+
+    // Any set of swaps that fixes a failing output has to touch at least one
+    // gate in that output's fan-in cone, otherwise the cone computes exactly
+    // what it did before. Only those swaps are tried at each level.
+    fn search_swaps<F>(
+        &mut self,
+        reference: &Reference<F>,
+        vectors: &[Vec<u64>],
+        depth: usize,
+        swaps: &mut Vec<(usize, usize)>,
+        swapped: &mut Vec<bool>,
+    ) -> bool
+    where
+        F: Fn(&[u64]) -> Vec<u64>,
+    {
+        let Some(mismatch) = self.find_mismatch(reference, vectors) else {
+            return true;
+        };
+        if depth == 0 {
+            return false;
+        }
+
+        let failing_output = reference.outputs[mismatch.first_failing_output()];
+        let cone = self.fan_in_cone(failing_output);
+        let mut in_cone: Vec<bool> = vec![false; self.connections.len()];
+        for &index in cone.iter() {
+            in_cone[index] = true;
+        }
+
+        for &a in cone.iter() {
+            for b in 0..self.connections.len() {
+                if a == b || swapped[a] || swapped[b] || (in_cone[b] && b < a) {
+                    continue;
+                }
+
+                self.swap_outputs(a, b);
+                swapped[a] = true;
+                swapped[b] = true;
+                swaps.push((a, b));
+
+                if self.search_swaps(reference, vectors, depth - 1, swaps, swapped) {
+                    return true;
+                }
+
+                swaps.pop();
+                swapped[a] = false;
+                swapped[b] = false;
+                self.swap_outputs(a, b);
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_circuit_with_width;
+    use crate::wire_kit::Wire;
+
+    const BITS: usize = 4;
+
+    type AdderCheck = (
+        Result<(), EquivalenceError>,
+        Result<Option<Vec<(String, String)>>, EquivalenceError>,
+    );
+
+    fn ripple_adder() -> String {
+        let mut instructions = String::from("x00 XOR y00 -> z00\nx00 AND y00 -> c00\n");
+        for bit in 1..BITS {
+            instructions.push_str(&format!(
+                "x{bit:02} XOR y{bit:02} -> t{bit:02}
+t{bit:02} XOR c{prev:02} -> z{bit:02}
+x{bit:02} AND y{bit:02} -> a{bit:02}
+t{bit:02} AND c{prev:02} -> b{bit:02}
+a{bit:02} OR b{bit:02} -> c{bit:02}
+",
+                bit = bit,
+                prev = bit - 1
+            ));
+        }
+        instructions.push_str(&format!("c{:02} -> z{:02}\n", BITS - 1, BITS));
+        instructions
+    }
+
+    fn adder_wires(prefix: char, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|bit| format!("{}{:02}", prefix, bit))
+            .collect()
+    }
+
+    fn add(inputs: &[u64]) -> Vec<u64> {
+        let (x_bits, y_bits) = inputs.split_at(BITS);
+        let to_number = |bits: &[u64]| {
+            bits.iter()
+                .enumerate()
+                .fold(0, |number, (bit, value)| number | (value << bit))
+        };
+        let sum = to_number(x_bits) + to_number(y_bits);
+
+        (0..=BITS).map(|bit| (sum >> bit) & 1).collect()
+    }
+
+    fn check_adder(instructions: &str, max_swaps: usize, vectors: TestVectors) -> AdderCheck {
+        let mut kit = parse_circuit_with_width(instructions, 1).expect("Should parse adder");
+
+        let input_names: Vec<String> = adder_wires('x', BITS)
+            .into_iter()
+            .chain(adder_wires('y', BITS))
+            .collect();
+        let output_names = adder_wires('z', BITS + 1);
+        let inputs: Vec<&str> = input_names.iter().map(String::as_str).collect();
+        let outputs: Vec<&str> = output_names.iter().map(String::as_str).collect();
+
+        let reference = Reference {
+            inputs: &inputs,
+            outputs: &outputs,
+            function: add,
+        };
+
+        (
+            kit.check_equivalence(&reference, &vectors),
+            kit.find_output_swaps(&reference, &vectors, max_swaps),
+        )
+    }
+
+    #[test]
+    fn test_check_equivalence() {
+        let (result, swaps) = check_adder(&ripple_adder(), 1, TestVectors::Exhaustive);
+
+        assert!(matches!(result, Ok(())));
+        assert_eq!(swaps, Ok(Some(vec![])));
+    }
+
+    #[test]
+    fn test_find_single_swap() {
+        let instructions = ripple_adder()
+            .replace("-> z02", "-> tmp")
+            .replace("-> c01", "-> z02")
+            .replace("-> tmp", "-> c01");
+
+        let (result, swaps) = check_adder(&instructions, 2, TestVectors::Exhaustive);
+
+        let Err(EquivalenceError::Mismatch(mismatch)) = result else {
+            panic!("Swapped adder should not match");
+        };
+        assert_ne!(
+            mismatch.actual,
+            mismatch
+                .expected
+                .iter()
+                .map(|&s| Some(s))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            swaps,
+            Ok(Some(vec![("c01".to_string(), "z02".to_string())]))
+        );
+    }
+
+    #[test]
+    fn test_find_two_swaps() {
+        let instructions = ripple_adder()
+            .replace("-> z01", "-> tmp")
+            .replace("-> a01", "-> z01")
+            .replace("-> tmp", "-> a01")
+            .replace("-> t03", "-> tmp")
+            .replace("-> b03", "-> t03")
+            .replace("-> tmp", "-> b03");
+
+        let (result, swaps) = check_adder(
+            &instructions,
+            2,
+            TestVectors::Random {
+                samples: 64,
+                seed: 7,
+            },
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            swaps,
+            Ok(Some(vec![
+                ("a01".to_string(), "z01".to_string()),
+                ("b03".to_string(), "t03".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_no_swaps_within_limit() {
+        let instructions = ripple_adder().replace("x01 AND y01 -> a01", "x01 OR y01 -> a01");
+
+        let (result, swaps) = check_adder(&instructions, 1, TestVectors::Exhaustive);
+
+        assert!(result.is_err());
+        assert_eq!(swaps, Ok(None));
+    }
+
+    #[test]
+    fn test_inputs_restored() {
+        let mut kit = parse_circuit_with_width("3 -> a\n5 -> b\na ADD b -> c\n", 8)
+            .expect("Should parse circuit");
+        let reference = Reference {
+            inputs: &["a", "b"],
+            outputs: &["c"],
+            function: |inputs: &[u64]| vec![inputs[0] + inputs[1]],
+        };
+        let vectors = TestVectors::Random {
+            samples: 16,
+            seed: 1,
+        };
+
+        assert_eq!(kit.check_equivalence(&reference, &vectors), Ok(()));
+        assert_eq!(
+            kit.find_output_swaps(&reference, &vectors, 1),
+            Ok(Some(vec![]))
+        );
+        assert_eq!(kit.wire.get("a"), Some(&Wire::Signal(3)));
+        assert_eq!(kit.wire.get("b"), Some(&Wire::Signal(5)));
+        assert!(matches!(kit.probe("c"), Ok(8)));
+    }
+
+    #[test]
+    fn test_exhaustive_too_many_bits() {
+        let mut kit = parse_circuit_with_width("a ADD b -> c\n", 16).expect("Should parse circuit");
+        let reference = Reference {
+            inputs: &["a", "b"],
+            outputs: &["c"],
+            function: |inputs: &[u64]| vec![inputs[0] + inputs[1]],
+        };
+
+        assert_eq!(
+            kit.check_equivalence(&reference, &TestVectors::Exhaustive),
+            Err(EquivalenceError::TooManyInputBits { bits: 32 })
+        );
+        assert_eq!(
+            kit.find_output_swaps(&reference, &TestVectors::Exhaustive, 1),
+            Err(EquivalenceError::TooManyInputBits { bits: 32 })
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeSet;
+
+use crate::wire_kit::{GateUtil, Input, Output, Wire, WiresKit};
+
+#[derive(Debug, Default)]
+pub struct ExportOptions {
+    pub cone_of: Option<String>,
+}
+
+struct GateNode {
+    id: String,
+    label: String,
+}
+
+struct Graph {
+    wires: Vec<(String, String)>,
+    gates: Vec<GateNode>,
+    edges: Vec<(String, String)>,
+}
+
+fn wire_id(wire_name: &str) -> String {
+    format!("w_{}", wire_name)
+}
+
+impl WiresKit {
+    fn wire_label(&self, wire_name: &str) -> String {
+        match self.wire.get(wire_name) {
+            Some(Wire::Signal(signal)) => format!("{} = {}", wire_name, signal),
+            _ => wire_name.to_string(),
+        }
+    }
+
+    fn export_graph(&self, options: &ExportOptions) -> Graph {
+        let gate_indices: Vec<usize> = match &options.cone_of {
+            Some(wire_name) => self.fan_in_cone(wire_name),
+            None => (0..self.connections.len()).collect(),
+        };
+
+        let mut wire_names: BTreeSet<&str> = BTreeSet::new();
+        let mut gates: Vec<GateNode> = Vec::new();
+        let mut edges: Vec<(String, String)> = Vec::new();
+
+        match &options.cone_of {
+            Some(wire_name) => {
+                wire_names.insert(wire_name);
+            }
+            None => wire_names.extend(self.wire.keys().map(String::as_str)),
+        }
+
+        for index in gate_indices {
+            let gate = &self.connections[index];
+            let id = format!("g{}", index);
+
+            // Operands are listed in order in the label, e.g. `SUB(x, 3)`, as
+            // edges alone don't tell which side of SUB or a shift a wire is
+            // on, and literal operands have no wire to draw an edge from.
+            let mut operands: Vec<String> = Vec::new();
+            for input in gate.get_inputs() {
+                match input {
+                    Input::Signal(signal) => operands.push(signal.to_string()),
+                    Input::Wire(wire_name) => {
+                        operands.push(wire_name.to_string());
+                        wire_names.insert(wire_name);
+                        edges.push((wire_id(wire_name), id.to_string()));
+                    }
+                }
+            }
+            let label = format!("{}({})", gate.get_name(), operands.join(", "));
+
+            let Output::Wire(output_wire) = gate.get_output();
+            wire_names.insert(output_wire);
+            edges.push((id.to_string(), wire_id(output_wire)));
+
+            gates.push(GateNode { id, label });
+        }
+
+        Graph {
+            wires: wire_names
+                .into_iter()
+                .map(|wire_name| (wire_id(wire_name), self.wire_label(wire_name)))
+                .collect(),
+            gates,
+            edges,
+        }
+    }
+
+    pub fn to_dot(&self, options: &ExportOptions) -> String {
+        let graph = self.export_graph(options);
+        let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n");
+
+        for (id, label) in graph.wires.iter() {
+            dot.push_str(&format!(
+                "    {} [shape=ellipse, label=\"{}\"];\n",
+                id, label
+            ));
+        }
+        for gate in graph.gates.iter() {
+            dot.push_str(&format!(
+                "    {} [shape=box, label=\"{}\"];\n",
+                gate.id, gate.label
+            ));
+        }
+        for (from, to) in graph.edges.iter() {
+            dot.push_str(&format!("    {} -> {};\n", from, to));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_mermaid(&self, options: &ExportOptions) -> String {
+        let graph = self.export_graph(options);
+        let mut mermaid = String::from("flowchart LR\n");
+
+        for (id, label) in graph.wires.iter() {
+            mermaid.push_str(&format!("    {}([\"{}\"])\n", id, label));
+        }
+        for gate in graph.gates.iter() {
+            mermaid.push_str(&format!("    {}[\"{}\"]\n", gate.id, gate.label));
+        }
+        for (from, to) in graph.edges.iter() {
+            mermaid.push_str(&format!("    {} --> {}\n", from, to));
+        }
+
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_circuit;
+
+    fn test_kit() -> WiresKit {
+        parse_circuit("123 -> x\n456 -> y\nx AND y -> d\nx LSHIFT 2 -> f\nNOT y -> i\n")
+            .expect("Should parse circuit")
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut kit = test_kit();
+
+        assert_eq!(
+            kit.to_dot(&ExportOptions::default()),
+            "digraph circuit {
+    rankdir=LR;
+    w_d [shape=ellipse, label=\"d\"];
+    w_f [shape=ellipse, label=\"f\"];
+    w_i [shape=ellipse, label=\"i\"];
+    w_x [shape=ellipse, label=\"x = 123\"];
+    w_y [shape=ellipse, label=\"y = 456\"];
+    g0 [shape=box, label=\"AND(x, y)\"];
+    g1 [shape=box, label=\"LSHIFT(x, 2)\"];
+    g2 [shape=box, label=\"NOT(y)\"];
+    w_x -> g0;
+    w_y -> g0;
+    g0 -> w_d;
+    w_x -> g1;
+    g1 -> w_f;
+    w_y -> g2;
+    g2 -> w_i;
+}
+"
+        );
+
+        kit.evaluate_circuit().expect("Should evaluate circuit");
+        let dot = kit.to_dot(&ExportOptions::default());
+        assert!(dot.contains("w_d [shape=ellipse, label=\"d = 72\"];"));
+        assert!(dot.contains("w_f [shape=ellipse, label=\"f = 492\"];"));
+        assert!(dot.contains("w_i [shape=ellipse, label=\"i = 65079\"];"));
+    }
+
+    #[test]
+    fn test_operand_order() {
+        let kit = parse_circuit("5 -> x\nx SUB 3 -> a\n3 SUB x -> b\n1 LSHIFT x -> c\n")
+            .expect("Should parse circuit");
+        let dot = kit.to_dot(&ExportOptions::default());
+
+        assert!(dot.contains("g0 [shape=box, label=\"SUB(x, 3)\"];"));
+        assert!(dot.contains("g1 [shape=box, label=\"SUB(3, x)\"];"));
+        assert!(dot.contains("g2 [shape=box, label=\"LSHIFT(1, x)\"];"));
+    }
+
+    #[test]
+    fn test_to_mermaid_cone() {
+        let kit = test_kit();
+        let options = ExportOptions {
+            cone_of: Some("f".to_string()),
+        };
+
+        assert_eq!(
+            kit.to_mermaid(&options),
+            "flowchart LR
+    w_f([\"f\"])
+    w_x([\"x = 123\"])
+    g1[\"LSHIFT(x, 2)\"]
+    w_x --> g1
+    g1 --> w_f
+"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod equivalence;
+pub mod export;
+pub mod optimizer;
+pub mod parser;
+#[cfg(test)]
+mod test_circuits;
+pub mod wire_kit;
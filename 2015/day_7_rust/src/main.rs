@@ -1,29 +1,26 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader, Error},
-};
-mod wire_kit;
+use std::{fs, io::Error};
 
-use wire_kit::{Wire, WiresKit};
+use day_7_rust::{parser::parse_circuit, wire_kit::Wire};
 
 fn main() -> Result<(), Error> {
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-
-    let mut kit = WiresKit::new();
-
-    for line in reader.lines().filter_map(Result::ok) {
-        kit.process_instruction(&line);
-    }
+    let instructions = fs::read_to_string("input.txt")?;
+
+    let mut kit = match parse_circuit(&instructions) {
+        Ok(kit) => kit,
+        Err(e) => {
+            println!("Parse errors:\n{}", e);
+            return Ok(());
+        }
+    };
 
     println!(
         "Starts evaluating circuit with {} wires and {} gates",
-        kit.wire.iter().count(),
+        kit.wire.len(),
         kit.connections.len()
     );
     match kit.evaluate_circuit() {
         Ok(_) => println!("Evaluation succes"),
-        Err(e) => println!("Evaluation error: {:?}", e),
+        Err(e) => println!("Evaluation error: {}", e),
     }
 
     let wire_a_signal = if let Some(a) = kit.wire.get("a").cloned() {
@@ -37,23 +34,13 @@ fn main() -> Result<(), Error> {
         Wire::Unknown => println!("Wire [a] is not found"),
     }
 
-    kit.reset_all_wires_expect_inputs();
-
-    kit.wire.insert("b".to_string(), wire_a_signal);
-    match kit.evaluate_circuit() {
-        Ok(_) => println!("Evaluation succes"),
-        Err(e) => println!("Evaluation error: {:?}", e),
+    if let Wire::Signal(s) = wire_a_signal {
+        kit.override_wire("b", s);
     }
 
-    let wire_a_signal = if let Some(a) = kit.wire.get("a").cloned() {
-        a
-    } else {
-        Wire::Unknown
-    };
-
-    match wire_a_signal {
-        Wire::Signal(s) => println!("Wire [a] has signal: {}", s),
-        Wire::Unknown => println!("Wire [a] is not found"),
+    match kit.probe("a") {
+        Ok(s) => println!("Wire [a] has signal: {}", s),
+        Err(e) => println!("Evaluation error: {}", e),
     }
 
     Ok(())
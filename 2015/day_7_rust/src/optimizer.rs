@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::wire_kit::{Gate, GateUtil, Input, Output, Wire, WiresKit};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct OptimizeReport {
+    pub constants_folded: usize,
+    pub relays_merged: usize,
+    pub dead_gates_removed: usize,
+}
+
+impl OptimizeReport {
+    pub fn gates_removed(&self) -> usize {
+        self.constants_folded + self.relays_merged + self.dead_gates_removed
+    }
+}
+
+impl WiresKit {
+    fn driven_wires(&self) -> HashSet<String> {
+        self.connections
+            .iter()
+            .map(|gate| gate.get_output_wire_name())
+            .collect()
+    }
+
+    fn fold_constants(&mut self) -> usize {
+        let mut folded = 0;
+
+        loop {
+            let driven = self.driven_wires();
+            let constants: HashMap<String, u64> = self
+                .wire
+                .iter()
+                .filter_map(|(wire_name, wire)| match wire {
+                    Wire::Signal(signal) if !driven.contains(wire_name) => {
+                        Some((wire_name.to_string(), *signal))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            for gate in self.connections.iter_mut() {
+                for input in gate.get_inputs_mut() {
+                    if let Input::Wire(wire_name) = input {
+                        if let Some(&signal) = constants.get(wire_name) {
+                            *input = Input::Signal(signal);
+                        }
+                    }
+                }
+            }
+
+            let results: Vec<Option<u64>> = self
+                .connections
+                .iter()
+                .map(|gate| {
+                    let is_constant = gate
+                        .get_inputs()
+                        .iter()
+                        .all(|input| matches!(input, Input::Signal(_)));
+                    if is_constant {
+                        self.evaluate_gate(gate).ok()
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            if results.iter().all(Option::is_none) {
+                break;
+            }
+
+            let gates = std::mem::take(&mut self.connections);
+            for (gate, result) in gates.into_iter().zip(results) {
+                match result {
+                    Some(signal) => {
+                        self.wire
+                            .insert(gate.get_output_wire_name(), Wire::Signal(signal));
+                        folded += 1;
+                    }
+                    None => self.connections.push(gate),
+                }
+            }
+        }
+
+        folded
+    }
+
+    fn merge_relays(&mut self, keep: &HashSet<&str>) -> usize {
+        let mut merged = 0;
+
+        while let Some(index) = self.connections.iter().position(|gate| {
+            matches!(
+                gate,
+                Gate::Relay(Input::Wire(source), Output::Wire(output))
+                    if source != output && !keep.contains(output.as_str())
+            )
+        }) {
+            let Gate::Relay(Input::Wire(source), Output::Wire(output)) =
+                self.connections.remove(index)
+            else {
+                unreachable!("Gate at {} was just matched as a relay", index);
+            };
+
+            for gate in self.connections.iter_mut() {
+                for input in gate.get_inputs_mut() {
+                    if matches!(input, Input::Wire(wire_name) if *wire_name == output) {
+                        *input = Input::Wire(source.to_string());
+                    }
+                }
+            }
+
+            self.wire.remove(&output);
+            self.source_lines.remove(&output);
+            merged += 1;
+        }
+
+        merged
+    }
+
+    fn remove_dead_gates(&mut self, keep: &HashSet<&str>) -> usize {
+        let mut live: Vec<bool> = vec![false; self.connections.len()];
+        for wire_name in keep.iter() {
+            for index in self.fan_in_cone(wire_name) {
+                live[index] = true;
+            }
+        }
+
+        let gates = std::mem::take(&mut self.connections);
+        let mut removed = 0;
+        for (gate, is_live) in gates.into_iter().zip(live) {
+            if is_live {
+                self.connections.push(gate);
+            } else {
+                removed += 1;
+            }
+        }
+
+        let mut referenced: HashSet<String> = keep.iter().map(|w| w.to_string()).collect();
+        for gate in self.connections.iter() {
+            referenced.insert(gate.get_output_wire_name());
+            for input in gate.get_inputs() {
+                if let Input::Wire(wire_name) = input {
+                    referenced.insert(wire_name.to_string());
+                }
+            }
+        }
+        self.wire
+            .retain(|wire_name, _| referenced.contains(wire_name));
+        self.source_lines
+            .retain(|wire_name, _| referenced.contains(wire_name));
+
+        removed
+    }
+
+    // Folds constant signals into the gates that read them, short-circuits
+    // relays and drops everything the `keep` wires do not depend on. Input
+    // signals are baked in, so overriding one afterwards has no effect on the
+    // gates it used to feed.
+    pub fn optimize(&mut self, keep: &[&str]) -> OptimizeReport {
+        let keep: HashSet<&str> = keep.iter().copied().collect();
+
+        let constants_folded = self.fold_constants();
+        let relays_merged = self.merge_relays(&keep);
+        let dead_gates_removed = self.remove_dead_gates(&keep);
+
+        OptimizeReport {
+            constants_folded,
+            relays_merged,
+            dead_gates_removed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_circuit;
+    use crate::test_circuits::circuit_strategy;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_fold_constants() {
+        let mut kit = parse_circuit(
+            "123 -> x\n456 -> y\nx AND y -> d\nx OR y -> e\nd LSHIFT 2 -> f\nNOT e -> g\nf AND z -> a",
+        )
+        .expect("Should parse circuit");
+
+        let report = kit.optimize(&["a"]);
+
+        assert_eq!(
+            report,
+            OptimizeReport {
+                constants_folded: 4,
+                relays_merged: 0,
+                dead_gates_removed: 0,
+            }
+        );
+        assert_eq!(report.gates_removed(), 4);
+        assert_eq!(kit.to_instructions(), "288 AND z -> a\n");
+
+        kit.override_wire("z", 0xff);
+        assert!(matches!(kit.probe("a"), Ok(32)));
+    }
+
+    #[test]
+    fn test_merge_relays_and_remove_dead_gates() {
+        let instructions =
+            "x AND y -> a\na -> b\nb -> c\nc OR x -> d\nNOT c -> e\nd -> out\ny -> unused";
+        let mut kit = parse_circuit(instructions).expect("Should parse circuit");
+
+        let report = kit.optimize(&["out"]);
+
+        assert_eq!(
+            report,
+            OptimizeReport {
+                constants_folded: 0,
+                relays_merged: 3,
+                dead_gates_removed: 1,
+            }
+        );
+        assert_eq!(
+            kit.to_instructions(),
+            "x AND y -> a\na OR x -> d\nd -> out\n"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_optimize_keeps_values(
+            instructions in circuit_strategy(),
+            keep_pick in prop::collection::vec(any::<prop::sample::Index>(), 1..4),
+            free_signals in prop::collection::vec(any::<u16>(), 2),
+        ) {
+            let mut kit = parse_circuit(&instructions).expect("Should parse generated circuit");
+            let mut optimized = parse_circuit(&instructions).expect("Should parse generated circuit");
+
+            let keep_names: Vec<String> = keep_pick
+                .iter()
+                .map(|pick| format!("g{}", pick.index(kit.connections.len())))
+                .collect();
+            let keep: Vec<&str> = keep_names.iter().map(String::as_str).collect();
+
+            let gates_before = optimized.connections.len();
+            let report = optimized.optimize(&keep);
+            prop_assert_eq!(gates_before - report.gates_removed(), optimized.connections.len());
+
+            // in3 and in4 carry no signal while optimizing, so gates reading
+            // them cannot be folded away and have to survive.
+            for (index, signal) in free_signals.iter().enumerate() {
+                let wire_name = format!("in{}", index + 3);
+                kit.override_wire(&wire_name, *signal as u64);
+                optimized.override_wire(&wire_name, *signal as u64);
+            }
+
+            for wire_name in keep.iter() {
+                prop_assert_eq!(kit.probe(wire_name).ok(), optimized.probe(wire_name).ok());
+            }
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt;
+
+use crate::wire_kit::{Gate, Input, Output, WiresKit};
+
+#[derive(Debug)]
+pub enum Instruction {
+    Signal(u64, String),
+    Gate(Gate),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnexpectedToken {
+        line: usize,
+        column: usize,
+        expected: String,
+        found: Option<String>,
+    },
+    MultipleDrivers {
+        line: usize,
+        column: usize,
+        wire: String,
+        first_line: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken {
+                line,
+                column,
+                expected,
+                found,
+            } => match found {
+                Some(found) => write!(
+                    f,
+                    "{}:{}: expected {}, found `{}`",
+                    line, column, expected, found
+                ),
+                None => write!(
+                    f,
+                    "{}:{}: expected {}, found end of line",
+                    line, column, expected
+                ),
+            },
+            ParseError::MultipleDrivers {
+                line,
+                column,
+                wire,
+                first_line,
+            } => write!(
+                f,
+                "{}:{}: wire [{}] is already driven by line {}",
+                line, column, wire, first_line
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+const EXPECTED_OPERATOR: &str =
+    "operator (AND, OR, XOR, NAND, NOR, ADD, SUB, LSHIFT, RSHIFT, MUX) or `->`";
+
+#[derive(Debug)]
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+}
+
+fn tokenize(s: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (index, c) in s.char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(index),
+            (true, Some(from)) => {
+                tokens.push(Token {
+                    text: &s[from..index],
+                    column: from + 1,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(from) = start {
+        tokens.push(Token {
+            text: &s[from..],
+            column: from + 1,
+        });
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    line: usize,
+    end_column: usize,
+    max_signal: u64,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str, line: usize, max_signal: u64) -> Self {
+        Self {
+            tokens: tokenize(s),
+            position: 0,
+            line,
+            end_column: s.len() + 1,
+            max_signal,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.position)
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let token = self.peek();
+        ParseError::UnexpectedToken {
+            line: self.line,
+            column: token.map_or(self.end_column, |t| t.column),
+            expected: expected.to_string(),
+            found: token.map(|t| t.text.to_string()),
+        }
+    }
+
+    fn is_wire_name(s: &str) -> bool {
+        s.starts_with(|c: char| c.is_ascii_lowercase())
+            && s.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    }
+
+    fn operand(&mut self) -> Result<Input, ParseError> {
+        const EXPECTED: &str = "wire name or signal";
+
+        let text = match self.peek() {
+            Some(token) => token.text,
+            None => return Err(self.error(EXPECTED)),
+        };
+
+        let input = if text.chars().all(|c| c.is_ascii_digit()) {
+            match text.parse::<u64>() {
+                Ok(signal) if signal <= self.max_signal => Input::Signal(signal),
+                _ => {
+                    let expected = format!("signal in range 0..={}", self.max_signal);
+                    return Err(self.error(&expected));
+                }
+            }
+        } else if Parser::is_wire_name(text) {
+            Input::Wire(text.to_string())
+        } else {
+            return Err(self.error(EXPECTED));
+        };
+
+        self.position += 1;
+        Ok(input)
+    }
+
+    fn arrow(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if token.text == "->" => {
+                self.position += 1;
+                Ok(())
+            }
+            _ => Err(self.error("`->`")),
+        }
+    }
+
+    fn output(&mut self) -> Result<(String, usize), ParseError> {
+        let (output, column) = match self.peek() {
+            Some(token) if Parser::is_wire_name(token.text) => {
+                (token.text.to_string(), token.column)
+            }
+            _ => return Err(self.error("output wire name")),
+        };
+        self.position += 1;
+
+        if self.peek().is_some() {
+            return Err(self.error("end of line"));
+        }
+
+        Ok((output, column))
+    }
+
+    fn instruction(&mut self) -> Result<(Instruction, usize), ParseError> {
+        if matches!(self.peek(), Some(token) if token.text == "NOT") {
+            self.position += 1;
+            let input = self.operand()?;
+            self.arrow()?;
+            let (output, column) = self.output()?;
+
+            return Ok((
+                Instruction::Gate(Gate::NOT(input, Output::Wire(output))),
+                column,
+            ));
+        }
+
+        let input_a = self.operand()?;
+
+        let operator = match self.peek() {
+            Some(token) if token.text == "->" => None,
+            Some(token) => Some(token.text),
+            None => return Err(self.error(EXPECTED_OPERATOR)),
+        };
+
+        let Some(operator) = operator else {
+            self.arrow()?;
+            let (output, column) = self.output()?;
+
+            let instruction = match input_a {
+                Input::Signal(signal) => Instruction::Signal(signal, output),
+                input => Instruction::Gate(Gate::Relay(input, Output::Wire(output))),
+            };
+            return Ok((instruction, column));
+        };
+
+        if operator == "MUX" {
+            self.position += 1;
+            let input_b = self.operand()?;
+            let input_c = self.operand()?;
+            self.arrow()?;
+            let (output, column) = self.output()?;
+
+            return Ok((
+                Instruction::Gate(Gate::MUX(input_a, input_b, input_c, Output::Wire(output))),
+                column,
+            ));
+        }
+
+        let gate: fn(Input, Input, Output) -> Gate = match operator {
+            "AND" => Gate::AND,
+            "OR" => Gate::OR,
+            "LSHIFT" => Gate::LSHIFT,
+            "RSHIFT" => Gate::RSHIFT,
+            "XOR" => Gate::XOR,
+            "NAND" => Gate::NAND,
+            "NOR" => Gate::NOR,
+            "ADD" => Gate::ADD,
+            "SUB" => Gate::SUB,
+            _ => return Err(self.error(EXPECTED_OPERATOR)),
+        };
+        self.position += 1;
+
+        let input_b = self.operand()?;
+        self.arrow()?;
+        let (output, column) = self.output()?;
+
+        Ok((
+            Instruction::Gate(gate(input_a, input_b, Output::Wire(output))),
+            column,
+        ))
+    }
+}
+
+// Parses a single instruction. Alongside it comes the column of the output
+// wire, so callers can point at it when the wire turns out to be driven twice.
+pub fn parse_instruction(
+    s: &str,
+    line: usize,
+    max_signal: u64,
+) -> Result<(Instruction, usize), ParseError> {
+    Parser::new(s, line, max_signal).instruction()
+}
+
+pub fn parse_circuit(s: &str) -> Result<WiresKit, ParseErrors> {
+    parse_circuit_with_width(s, 16)
+}
+
+pub fn parse_circuit_with_width(s: &str, width: u32) -> Result<WiresKit, ParseErrors> {
+    let mut kit = WiresKit::with_width(width);
+    let mut errors: Vec<ParseError> = Vec::new();
+
+    for line in s.lines() {
+        if line.trim().is_empty() {
+            kit.skip_line();
+            continue;
+        }
+        if let Err(e) = kit.try_process_instruction(line) {
+            errors.push(e);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(kit)
+    } else {
+        Err(ParseErrors(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire_kit::Wire;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("  x AND\ty ->  d ");
+        let tokens: Vec<(&str, usize)> = tokens.iter().map(|t| (t.text, t.column)).collect();
+        assert_eq!(
+            tokens,
+            vec![("x", 3), ("AND", 5), ("y", 9), ("->", 11), ("d", 15)]
+        );
+    }
+
+    #[test]
+    fn test_parse_circuit() {
+        let kit = parse_circuit("123 -> x\n456 -> y\n\nx AND y -> d\nNOT x -> h\n")
+            .expect("Should parse circuit");
+
+        assert!(matches!(kit.wire.get("x"), Some(Wire::Signal(123))));
+        assert!(matches!(kit.wire.get("y"), Some(Wire::Signal(456))));
+        assert_eq!(kit.connections.len(), 2);
+        assert_eq!(kit.source_lines.get("d"), Some(&4));
+        assert_eq!(kit.source_lines.get("h"), Some(&5));
+    }
+
+    #[test]
+    fn test_parse_circuit_errors() {
+        let errors = parse_circuit(
+            "123 -> x\nx XNOR y -> d\nNOT -> e\nx AND y d\n70000 -> f\nx OR y -> G\nx -> y z",
+        )
+        .expect_err("Should fail to parse circuit");
+
+        assert_eq!(
+            errors.0,
+            vec![
+                ParseError::UnexpectedToken {
+                    line: 2,
+                    column: 3,
+                    expected: EXPECTED_OPERATOR.to_string(),
+                    found: Some("XNOR".to_string()),
+                },
+                ParseError::UnexpectedToken {
+                    line: 3,
+                    column: 5,
+                    expected: "wire name or signal".to_string(),
+                    found: Some("->".to_string()),
+                },
+                ParseError::UnexpectedToken {
+                    line: 4,
+                    column: 9,
+                    expected: "`->`".to_string(),
+                    found: Some("d".to_string()),
+                },
+                ParseError::UnexpectedToken {
+                    line: 5,
+                    column: 1,
+                    expected: "signal in range 0..=65535".to_string(),
+                    found: Some("70000".to_string()),
+                },
+                ParseError::UnexpectedToken {
+                    line: 6,
+                    column: 11,
+                    expected: "output wire name".to_string(),
+                    found: Some("G".to_string()),
+                },
+                ParseError::UnexpectedToken {
+                    line: 7,
+                    column: 8,
+                    expected: "end of line".to_string(),
+                    found: Some("z".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_circuit_end_of_line() {
+        let errors = parse_circuit("x AND").expect_err("Should fail to parse circuit");
+
+        assert_eq!(
+            errors.0,
+            vec![ParseError::UnexpectedToken {
+                line: 1,
+                column: 6,
+                expected: "wire name or signal".to_string(),
+                found: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_circuit_multiple_drivers() {
+        let errors = parse_circuit("123 -> x\nx AND y -> d\n1 -> y\nNOT x -> d\n5 -> x")
+            .expect_err("Should fail to parse circuit");
+
+        assert_eq!(
+            errors.0,
+            vec![
+                ParseError::MultipleDrivers {
+                    line: 4,
+                    column: 10,
+                    wire: "d".to_string(),
+                    first_line: 2,
+                },
+                ParseError::MultipleDrivers {
+                    line: 5,
+                    column: 6,
+                    wire: "x".to_string(),
+                    first_line: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_circuit_extended_gates() {
+        let kit = parse_circuit("x00 XOR y00 -> z00\nsel MUX x00 1 -> z01\nx00 SUB 3 -> z02")
+            .expect("Should parse circuit");
+        assert_eq!(kit.connections.len(), 3);
+        assert!(matches!(
+            &kit.connections[1],
+            Gate::MUX(
+                Input::Wire(select),
+                Input::Wire(input_a),
+                Input::Signal(1),
+                Output::Wire(output)
+            ) if select == "sel" && input_a == "x00" && output == "z01"
+        ));
+
+        let errors = parse_circuit("sel MUX a -> z\n0x -> y").expect_err("Should fail to parse");
+        assert_eq!(
+            errors.0,
+            vec![
+                ParseError::UnexpectedToken {
+                    line: 1,
+                    column: 11,
+                    expected: "wire name or signal".to_string(),
+                    found: Some("->".to_string()),
+                },
+                ParseError::UnexpectedToken {
+                    line: 2,
+                    column: 1,
+                    expected: "wire name or signal".to_string(),
+                    found: Some("0x".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use proptest::prelude::*;
+
+const GATE_NAMES: [&str; 11] = [
+    "NOT", "AND", "OR", "LSHIFT", "RSHIFT", "XOR", "NAND", "NOR", "ADD", "SUB", "MUX",
+];
+
+// Acyclic circuits of gates `g0`, `g1`, ... reading the inputs `in0` to
+// `in4`. Only `in0` to `in2` get a signal. Operands refer to earlier gates
+// only, and relays always read a wire since `5 -> g0` would parse as an
+// input signal instead.
+pub(crate) fn circuit_strategy() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec(any::<u16>(), 3),
+        prop::collection::vec(
+            (
+                0..GATE_NAMES.len() + 1,
+                prop::collection::vec((0..3_usize, any::<u16>(), any::<prop::sample::Index>()), 3),
+            ),
+            1..32,
+        ),
+    )
+        .prop_map(|(signals, gates)| {
+            let mut lines: Vec<String> = signals
+                .iter()
+                .enumerate()
+                .map(|(index, signal)| format!("{} -> in{}", signal, index))
+                .collect();
+
+            for (index, (kind, operands)) in gates.iter().enumerate() {
+                let operands: Vec<String> = operands
+                    .iter()
+                    .map(|(source, signal, pick)| match source {
+                        0 => (signal % 20).to_string(),
+                        1 if index > 0 => format!("g{}", pick.index(index)),
+                        _ => format!("in{}", pick.index(5)),
+                    })
+                    .collect();
+                let output = format!("g{}", index);
+
+                let line = match GATE_NAMES.get(*kind) {
+                    None => match &operands[0] {
+                        operand if operand.starts_with(char::is_numeric) => {
+                            format!("in3 -> {}", output)
+                        }
+                        operand => format!("{} -> {}", operand, output),
+                    },
+                    Some(&"NOT") => format!("NOT {} -> {}", operands[0], output),
+                    Some(&"MUX") => format!(
+                        "{} MUX {} {} -> {}",
+                        operands[0], operands[1], operands[2], output
+                    ),
+                    Some(name) => {
+                        format!("{} {} {} -> {}", operands[0], name, operands[1], output)
+                    }
+                };
+                lines.push(line);
+            }
+
+            lines.join("\n")
+        })
+}
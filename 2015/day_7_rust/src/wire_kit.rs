@@ -1,39 +1,99 @@
-use regex::Regex;
-use std::collections::HashMap;
+use crate::parser::{parse_instruction, Instruction, ParseError};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Wire {
     Unknown,
-    Signal(u16),
+    Signal(u64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Input {
     Wire(String),
-    Signal(u16),
+    Signal(u64),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Output {
     Wire(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Gate {
     Relay(Input, Output),
     NOT(Input, Output),
     AND(Input, Input, Output),
     OR(Input, Input, Output),
-    LSHIFT(Input, u16, Output),
-    RSHIFT(Input, u16, Output),
+    LSHIFT(Input, Input, Output),
+    RSHIFT(Input, Input, Output),
+    XOR(Input, Input, Output),
+    NAND(Input, Input, Output),
+    NOR(Input, Input, Output),
+    ADD(Input, Input, Output),
+    SUB(Input, Input, Output),
+    MUX(Input, Input, Input, Output),
 }
 
-trait GateUtil {
+pub(crate) trait GateUtil {
+    fn get_name(&self) -> &'static str;
+    fn get_inputs(&self) -> Vec<&Input>;
+    fn get_inputs_mut(&mut self) -> Vec<&mut Input>;
     fn get_output(&self) -> &Output;
+    fn get_output_mut(&mut self) -> &mut Output;
     fn get_output_wire_name(&self) -> String;
 }
 
 impl GateUtil for Gate {
+    fn get_name(&self) -> &'static str {
+        match self {
+            Gate::Relay(_, _) => "Relay",
+            Gate::NOT(_, _) => "NOT",
+            Gate::AND(_, _, _) => "AND",
+            Gate::OR(_, _, _) => "OR",
+            Gate::LSHIFT(_, _, _) => "LSHIFT",
+            Gate::RSHIFT(_, _, _) => "RSHIFT",
+            Gate::XOR(_, _, _) => "XOR",
+            Gate::NAND(_, _, _) => "NAND",
+            Gate::NOR(_, _, _) => "NOR",
+            Gate::ADD(_, _, _) => "ADD",
+            Gate::SUB(_, _, _) => "SUB",
+            Gate::MUX(_, _, _, _) => "MUX",
+        }
+    }
+    fn get_inputs(&self) -> Vec<&Input> {
+        match self {
+            Gate::NOT(input, _) | Gate::Relay(input, _) => vec![input],
+            Gate::AND(input_a, input_b, _)
+            | Gate::OR(input_a, input_b, _)
+            | Gate::LSHIFT(input_a, input_b, _)
+            | Gate::RSHIFT(input_a, input_b, _)
+            | Gate::XOR(input_a, input_b, _)
+            | Gate::NAND(input_a, input_b, _)
+            | Gate::NOR(input_a, input_b, _)
+            | Gate::ADD(input_a, input_b, _)
+            | Gate::SUB(input_a, input_b, _) => vec![input_a, input_b],
+            Gate::MUX(select, input_a, input_b, _) => vec![select, input_a, input_b],
+        }
+    }
+    fn get_inputs_mut(&mut self) -> Vec<&mut Input> {
+        match self {
+            Gate::NOT(input, _) | Gate::Relay(input, _) => vec![input],
+            Gate::AND(input_a, input_b, _)
+            | Gate::OR(input_a, input_b, _)
+            | Gate::LSHIFT(input_a, input_b, _)
+            | Gate::RSHIFT(input_a, input_b, _)
+            | Gate::XOR(input_a, input_b, _)
+            | Gate::NAND(input_a, input_b, _)
+            | Gate::NOR(input_a, input_b, _)
+            | Gate::ADD(input_a, input_b, _)
+            | Gate::SUB(input_a, input_b, _) => vec![input_a, input_b],
+            Gate::MUX(select, input_a, input_b, _) => vec![select, input_a, input_b],
+        }
+    }
     fn get_output(&self) -> &Output {
         match self {
             Gate::NOT(_, output)
@@ -41,7 +101,29 @@ impl GateUtil for Gate {
             | Gate::AND(_, _, output)
             | Gate::OR(_, _, output)
             | Gate::LSHIFT(_, _, output)
-            | Gate::RSHIFT(_, _, output) => output,
+            | Gate::RSHIFT(_, _, output)
+            | Gate::XOR(_, _, output)
+            | Gate::NAND(_, _, output)
+            | Gate::NOR(_, _, output)
+            | Gate::ADD(_, _, output)
+            | Gate::SUB(_, _, output)
+            | Gate::MUX(_, _, _, output) => output,
+        }
+    }
+    fn get_output_mut(&mut self) -> &mut Output {
+        match self {
+            Gate::NOT(_, output)
+            | Gate::Relay(_, output)
+            | Gate::AND(_, _, output)
+            | Gate::OR(_, _, output)
+            | Gate::LSHIFT(_, _, output)
+            | Gate::RSHIFT(_, _, output)
+            | Gate::XOR(_, _, output)
+            | Gate::NAND(_, _, output)
+            | Gate::NOR(_, _, output)
+            | Gate::ADD(_, _, output)
+            | Gate::SUB(_, _, output)
+            | Gate::MUX(_, _, _, output) => output,
         }
     }
     fn get_output_wire_name(&self) -> String {
@@ -50,7 +132,44 @@ impl GateUtil for Gate {
     }
 }
 
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Wire(wire_name) => write!(f, "{}", wire_name),
+            Input::Signal(signal) => write!(f, "{}", signal),
+        }
+    }
+}
+
+// Prints a gate in the same grammar `process_instruction` accepts. The only
+// exception is a relay fed by a literal, which reads back as an input signal.
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Output::Wire(output) = self.get_output();
+
+        match self {
+            Gate::Relay(input, _) => write!(f, "{} -> {}", input, output),
+            Gate::NOT(input, _) => write!(f, "NOT {} -> {}", input, output),
+            Gate::MUX(select, input_a, input_b, _) => {
+                write!(f, "{} MUX {} {} -> {}", select, input_a, input_b, output)
+            }
+            _ => {
+                let inputs = self.get_inputs();
+                write!(
+                    f,
+                    "{} {} {} -> {}",
+                    inputs[0],
+                    self.get_name(),
+                    inputs[1],
+                    output
+                )
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Process {
     Input,
     Relay,
@@ -59,30 +178,128 @@ pub enum Process {
     OR,
     LSHIFT,
     RSHIFT,
+    XOR,
+    NAND,
+    NOR,
+    ADD,
+    SUB,
+    MUX,
     Error,
 }
 
 #[derive(Debug)]
 pub enum EvaluateErrors {
-    NotInputError,
-    AndInputBError,
-    OrInputAError,
-    OrInputBError,
-    ShiftInputError,
-    ShiftDirectionError,
+    UnresolvedOperand {
+        line: Option<usize>,
+        operand: usize,
+        wire: String,
+    },
+    UndrivenWires(Vec<String>),
+    CombinationalLoop(Vec<String>),
 }
 
+impl fmt::Display for EvaluateErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaluateErrors::UndrivenWires(wires) => {
+                write!(f, "wires without a driver: {}", wires.join(", "))
+            }
+            EvaluateErrors::CombinationalLoop(wires) => {
+                write!(f, "combinational loop through wires: {}", wires.join(", "))
+            }
+            EvaluateErrors::UnresolvedOperand {
+                line,
+                operand,
+                wire,
+            } => {
+                write!(f, "operand {} ({}) has no signal", operand, wire)?;
+                if let Some(line) = line {
+                    write!(f, " in gate from line {}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct WiresKit {
     pub wire: HashMap<String, Wire>,
     pub connections: Vec<Gate>,
+    pub source_lines: HashMap<String, usize>,
+    lines_processed: usize,
+    width: u32,
+}
+
+impl fmt::Display for WiresKit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_instructions())
+    }
+}
+
+impl Default for WiresKit {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl WiresKit {
     pub fn new() -> Self {
+        WiresKit::with_width(16)
+    }
+
+    pub fn with_width(width: u32) -> Self {
+        assert!(
+            (1..=64).contains(&width),
+            "Bus width must be between 1 and 64 bits, got {}",
+            width
+        );
+
         Self {
             wire: HashMap::new(),
             connections: Vec::new(),
+            source_lines: HashMap::new(),
+            lines_processed: 0,
+            width,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+
+    // Input signals come first, sorted by wire name, followed by the gates in
+    // `connections` order. Signals computed for gate outputs are left out.
+    pub fn to_instructions(&self) -> String {
+        let driven: HashSet<String> = self
+            .connections
+            .iter()
+            .map(|gate| gate.get_output_wire_name())
+            .collect();
+
+        let mut inputs: Vec<(&String, u64)> = self
+            .wire
+            .iter()
+            .filter_map(|(wire_name, wire)| match wire {
+                Wire::Signal(signal) if !driven.contains(wire_name) => Some((wire_name, *signal)),
+                _ => None,
+            })
+            .collect();
+        inputs.sort();
+
+        let mut instructions = String::new();
+        for (wire_name, signal) in inputs {
+            instructions.push_str(&format!("{} -> {}\n", signal, wire_name));
         }
+        for gate in self.connections.iter() {
+            instructions.push_str(&format!("{}\n", gate));
+        }
+
+        instructions
     }
 
     pub fn reset_all_wires_expect_inputs(&mut self) {
@@ -95,270 +312,373 @@ impl WiresKit {
     pub fn evaluate_circuit(&mut self) -> Result<(), EvaluateErrors> {
         self.reset_all_wires_expect_inputs();
 
-        loop {
-            for gate in self.connections.iter() {
-                match gate {
-                    Gate::Relay(input, output) | Gate::NOT(input, output) => {
-                        let input_wire: String = match input {
-                            Input::Wire(input_wire_name) => input_wire_name.to_string(),
-                            _ => return Err(EvaluateErrors::NotInputError),
-                        };
-
-                        if let Some(input_value) = self.wire.get(&input_wire) {
-                            if let Wire::Signal(signal) = input_value {
-                                let Output::Wire(output_wire) = output;
-                                let result = if let Gate::NOT(_, _) = gate {
-                                    !signal
-                                } else {
-                                    *signal
-                                };
-                                self.wire
-                                    .insert(output_wire.to_string(), Wire::Signal(result));
-                            }
-                        }
-                    }
-                    Gate::AND(input_a, input_b, output) => {
-                        let input_wire_b: String = match input_b {
-                            Input::Wire(wire) => wire.to_string(),
-                            _ => return Err(EvaluateErrors::AndInputBError),
-                        };
-
-                        let input_value_a = match input_a {
-                            Input::Signal(signal) => &Wire::Signal(*signal),
-                            Input::Wire(wire) => self.wire.get(wire).unwrap(),
-                        };
-
-                        let input_value_b = self.wire.get(&input_wire_b).unwrap();
-
-                        match (input_value_a, input_value_b) {
-                            (Wire::Signal(signal_a), Wire::Signal(signal_b)) => {
-                                let result = signal_a & signal_b;
-                                let Output::Wire(output_wire) = output;
-                                self.wire
-                                    .insert(output_wire.to_string(), Wire::Signal(result));
-                            }
-                            _ => {}
-                        }
-                    }
-                    Gate::OR(input_a, input_b, output) => {
-                        let input_wire_a: String = match input_a {
-                            Input::Wire(wire) => wire.to_string(),
-                            _ => return Err(EvaluateErrors::OrInputAError),
-                        };
-                        let input_wire_b: String = match input_b {
-                            Input::Wire(wire) => wire.to_string(),
-                            _ => return Err(EvaluateErrors::OrInputBError),
-                        };
-
-                        let input_value_a = self.wire.get(&input_wire_a).unwrap();
-                        let input_value_b = self.wire.get(&input_wire_b).unwrap();
-
-                        match (input_value_a, input_value_b) {
-                            (Wire::Signal(signal_a), Wire::Signal(signal_b)) => {
-                                let result = signal_a | signal_b;
-                                let Output::Wire(output_wire) = output;
-                                self.wire
-                                    .insert(output_wire.to_string(), Wire::Signal(result));
-                            }
-                            _ => {}
-                        }
-                    }
-                    Gate::LSHIFT(input, factor, output) | Gate::RSHIFT(input, factor, output) => {
-                        let input_wire: String = match input {
-                            Input::Wire(wire) => wire.to_string(),
-                            _ => return Err(EvaluateErrors::ShiftInputError),
-                        };
-                        let input_value = self.wire.get(&input_wire).unwrap();
-
-                        if let Wire::Signal(input_signal) = input_value {
-                            let Output::Wire(output_wire) = output;
-
-                            let result = match gate {
-                                Gate::LSHIFT(_, _, _) => input_signal << factor,
-                                Gate::RSHIFT(_, _, _) => input_signal >> factor,
-                                _ => {
-                                    return Err(EvaluateErrors::ShiftDirectionError);
-                                }
-                            };
-                            self.wire
-                                .insert(output_wire.to_string(), Wire::Signal(result));
-                        }
-                    }
+        for index in self.topological_order()? {
+            let gate = &self.connections[index];
+            let result = self.evaluate_gate(gate)?;
+            self.wire
+                .insert(gate.get_output_wire_name(), Wire::Signal(result));
+        }
+
+        Ok(())
+    }
+
+    fn topological_order(&self) -> Result<Vec<usize>, EvaluateErrors> {
+        let mut drivers: HashMap<&str, usize> = HashMap::new();
+        for (index, gate) in self.connections.iter().enumerate() {
+            let Output::Wire(wire_name) = gate.get_output();
+            drivers.insert(wire_name, index);
+        }
+
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); self.connections.len()];
+        let mut in_degree: Vec<usize> = vec![0; self.connections.len()];
+        let mut undriven: Vec<String> = Vec::new();
+
+        for (index, gate) in self.connections.iter().enumerate() {
+            for input in gate.get_inputs() {
+                let Input::Wire(wire_name) = input else {
+                    continue;
+                };
+                if let Some(&driver) = drivers.get(wire_name.as_str()) {
+                    dependents[driver].push(index);
+                    in_degree[index] += 1;
+                } else if !matches!(self.wire.get(wire_name), Some(Wire::Signal(_))) {
+                    undriven.push(wire_name.to_string());
                 }
             }
+        }
 
-            let unknown_wires = self
-                .wire
-                .values()
-                .filter(|w| matches!(w, Wire::Unknown))
-                .count();
-            if unknown_wires == 0 {
+        if !undriven.is_empty() {
+            undriven.sort();
+            undriven.dedup();
+            return Err(EvaluateErrors::UndrivenWires(undriven));
+        }
+
+        let mut queue: VecDeque<usize> = (0..self.connections.len())
+            .filter(|&index| in_degree[index] == 0)
+            .collect();
+        let mut order: Vec<usize> = Vec::with_capacity(self.connections.len());
+
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for &dependent in dependents[index].iter() {
+                in_degree[dependent] -= 1;
+                if in_degree[dependent] == 0 {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        if order.len() < self.connections.len() {
+            return Err(EvaluateErrors::CombinationalLoop(
+                self.wires_in_loops(&in_degree, &dependents),
+            ));
+        }
+
+        Ok(order)
+    }
+
+    // Gates left with a non-zero in-degree after sorting are either part of a
+    // loop or fed by one. Peeling off the ones that feed nothing else that is
+    // still left keeps only the wires that actually take part in a loop.
+    fn wires_in_loops(&self, in_degree: &[usize], dependents: &[Vec<usize>]) -> Vec<String> {
+        let mut remaining: Vec<bool> = in_degree.iter().map(|&degree| degree > 0).collect();
+
+        loop {
+            let leaves: Vec<usize> = (0..remaining.len())
+                .filter(|&index| {
+                    remaining[index] && !dependents[index].iter().any(|&d| remaining[d])
+                })
+                .collect();
+            if leaves.is_empty() {
                 break;
             }
+            for index in leaves {
+                remaining[index] = false;
+            }
         }
 
-        Ok(())
+        let mut wires: Vec<String> = self
+            .connections
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| remaining[*index])
+            .map(|(_, gate)| gate.get_output_wire_name())
+            .collect();
+        wires.sort();
+        wires
     }
 
-    pub fn process_instruction(&mut self, s: &str) -> Process {
-        let input_pattern = Regex::new(r"^(?P<signal>\d+) -> (?P<wire>[a-z]+)$").unwrap();
-        let relay_pattern = Regex::new(r"^(?P<input>[a-z]+) -> (?P<output>[a-z]+)$").unwrap();
-        let not_pattern = Regex::new(r"^NOT (?P<input>[a-z]+) -> (?P<output>[a-z]+)$").unwrap();
-        let and_pattern = Regex::new(
-            r"^(?P<input_a>([a-z]+|\d+)) AND (?P<input_b>[a-z]+) -> (?P<output>[a-z]+)$",
-        )
-        .unwrap();
-        let or_pattern =
-            Regex::new(r"^(?P<input_a>[a-z]+) OR (?P<input_b>[a-z]+) -> (?P<output>[a-z]+)$")
-                .unwrap();
-        let shift_pattern = Regex::new(
-            r"^(?P<input>[a-z]+) (?P<direction>(L|R)SHIFT) (?P<factor>\d+) -> (?P<output>[a-z]+)$",
-        )
-        .unwrap();
-
-        if let Some(caps) = input_pattern.captures(s) {
-            let signal: Wire = match caps.name("signal") {
-                Some(signal) => match signal.as_str().parse::<u16>() {
-                    Ok(parsed_signal) => Wire::Signal(parsed_signal),
-                    Err(_) => return Process::Error,
-                },
-                None => return Process::Error,
-            };
-            let wire_name: String = match caps.name("wire") {
-                Some(wire_name) => wire_name.as_str().to_string(),
-                None => return Process::Error,
+    // Indices of every gate that `wire` transitively depends on, in the
+    // order they appear in `connections`.
+    pub fn fan_in_cone(&self, wire: &str) -> Vec<usize> {
+        let drivers: HashMap<&str, usize> = self
+            .connections
+            .iter()
+            .enumerate()
+            .map(|(index, gate)| {
+                let Output::Wire(wire_name) = gate.get_output();
+                (wire_name.as_str(), index)
+            })
+            .collect();
+
+        let mut in_cone: Vec<bool> = vec![false; self.connections.len()];
+        let mut queue: VecDeque<&str> = VecDeque::from([wire]);
+
+        while let Some(wire_name) = queue.pop_front() {
+            let Some(&index) = drivers.get(wire_name) else {
+                continue;
             };
+            if in_cone[index] {
+                continue;
+            }
+            in_cone[index] = true;
 
-            self.wire.insert(wire_name, signal);
+            for input in self.connections[index].get_inputs() {
+                if let Input::Wire(input_wire) = input {
+                    queue.push_back(input_wire);
+                }
+            }
+        }
 
-            return Process::Input;
-        };
+        (0..self.connections.len())
+            .filter(|&index| in_cone[index])
+            .collect()
+    }
 
-        if let Some(caps) = relay_pattern.captures(s) {
-            let input: Input = match caps.name("input") {
-                Some(input) => Input::Wire(input.as_str().to_string()),
-                None => return Process::Error,
-            };
+    // Resolves only the fan-in of `wire`. Every signal already present in
+    // `self.wire` is trusted as-is, and every gate output computed on the way
+    // is cached there, so repeated probes only pay for what is still unknown.
+    pub fn probe(&mut self, wire: &str) -> Result<u64, EvaluateErrors> {
+        let drivers: HashMap<String, usize> = self
+            .connections
+            .iter()
+            .enumerate()
+            .map(|(index, gate)| (gate.get_output_wire_name(), index))
+            .collect();
+        let mut in_progress: Vec<String> = Vec::new();
+
+        self.probe_wire(wire, &drivers, &mut in_progress)
+    }
 
-            let output: Output = match caps.name("output") {
-                Some(output) => Output::Wire(output.as_str().to_string()),
-                None => return Process::Error,
-            };
+    fn probe_wire(
+        &mut self,
+        wire: &str,
+        drivers: &HashMap<String, usize>,
+        in_progress: &mut Vec<String>,
+    ) -> Result<u64, EvaluateErrors> {
+        if let Some(signal) = self.get_signal(wire) {
+            return Ok(signal);
+        }
 
-            let gate = Gate::Relay(input, output);
+        let Some(&index) = drivers.get(wire) else {
+            return Err(EvaluateErrors::UndrivenWires(vec![wire.to_string()]));
+        };
 
-            self.connections.push(gate);
+        if let Some(position) = in_progress.iter().position(|w| w == wire) {
+            let mut wires = in_progress[position..].to_vec();
+            wires.sort();
+            return Err(EvaluateErrors::CombinationalLoop(wires));
+        }
 
-            return Process::Relay;
+        let input_wires: Vec<String> = self.connections[index]
+            .get_inputs()
+            .into_iter()
+            .filter_map(|input| match input {
+                Input::Wire(wire_name) => Some(wire_name.to_string()),
+                Input::Signal(_) => None,
+            })
+            .collect();
+
+        in_progress.push(wire.to_string());
+        for input_wire in input_wires.iter() {
+            self.probe_wire(input_wire, drivers, in_progress)?;
         }
+        in_progress.pop();
 
-        if let Some(caps) = not_pattern.captures(s) {
-            let input: Input = match caps.name("input") {
-                Some(input) => Input::Wire(input.as_str().to_string()),
-                None => return Process::Error,
-            };
-            let output: Output = match caps.name("output") {
-                Some(output) => Output::Wire(output.as_str().to_string()),
-                None => return Process::Error,
-            };
+        let result = self.evaluate_gate(&self.connections[index])?;
+        self.wire.insert(wire.to_string(), Wire::Signal(result));
 
-            let gate = Gate::NOT(input, output);
+        Ok(result)
+    }
 
-            self.connections.push(gate);
+    // Forgets every cached signal that depends on `wire`, leaving the rest of
+    // the board untouched for the next `probe`.
+    pub fn invalidate_downstream(&mut self, wire: &str) {
+        let mut readers: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, gate) in self.connections.iter().enumerate() {
+            for input in gate.get_inputs() {
+                if let Input::Wire(wire_name) = input {
+                    readers.entry(wire_name).or_default().push(index);
+                }
+            }
+        }
 
-            return Process::NOT;
-        };
+        let mut visited: Vec<bool> = vec![false; self.connections.len()];
+        let mut queue: VecDeque<&str> = VecDeque::from([wire]);
+        let mut invalidated: Vec<String> = Vec::new();
 
-        if let Some(caps) = and_pattern.captures(s) {
-            let input_a: Input = match caps.name("input_a") {
-                Some(input) => match input.as_str().parse::<u16>() {
-                    Ok(v) => Input::Signal(v),
-                    Err(_) => Input::Wire(input.as_str().to_string()),
-                },
-                None => return Process::Error,
-            };
-            let input_b: Input = match caps.name("input_b") {
-                Some(input) => Input::Wire(input.as_str().to_string()),
-                None => return Process::Error,
-            };
-            let output: Output = match caps.name("output") {
-                Some(output) => Output::Wire(output.as_str().to_string()),
-                None => return Process::Error,
-            };
+        while let Some(wire_name) = queue.pop_front() {
+            for &index in readers.get(wire_name).into_iter().flatten() {
+                if visited[index] {
+                    continue;
+                }
+                visited[index] = true;
 
-            let gate = Gate::AND(input_a, input_b, output);
+                let Output::Wire(output_wire) = self.connections[index].get_output();
+                invalidated.push(output_wire.to_string());
+                queue.push_back(output_wire);
+            }
+        }
 
-            self.connections.push(gate);
+        for wire_name in invalidated {
+            self.wire.insert(wire_name, Wire::Unknown);
+        }
+    }
 
-            return Process::AND;
-        };
+    pub fn override_wire(&mut self, wire: &str, signal: u64) {
+        self.wire
+            .insert(wire.to_string(), Wire::Signal(signal & self.mask()));
+        self.invalidate_downstream(wire);
+    }
 
-        if let Some(caps) = or_pattern.captures(s) {
-            let input_a: Input = match caps.name("input_a") {
-                Some(input) => Input::Wire(input.as_str().to_string()),
-                None => return Process::Error,
-            };
-            let input_b: Input = match caps.name("input_b") {
-                Some(input) => Input::Wire(input.as_str().to_string()),
-                None => return Process::Error,
-            };
-            let output: Output = match caps.name("output") {
-                Some(output) => Output::Wire(output.as_str().to_string()),
-                None => return Process::Error,
-            };
+    fn get_signal(&self, wire_name: &str) -> Option<u64> {
+        match self.wire.get(wire_name) {
+            Some(Wire::Signal(signal)) => Some(*signal),
+            _ => None,
+        }
+    }
 
-            let gate = Gate::OR(input_a, input_b, output);
+    fn resolve_operand(
+        &self,
+        gate: &Gate,
+        operand: usize,
+        input: &Input,
+    ) -> Result<u64, EvaluateErrors> {
+        match input {
+            Input::Signal(signal) => Ok(*signal & self.mask()),
+            Input::Wire(wire_name) => {
+                self.get_signal(wire_name)
+                    .ok_or_else(|| EvaluateErrors::UnresolvedOperand {
+                        line: self.source_lines.get(&gate.get_output_wire_name()).copied(),
+                        operand,
+                        wire: wire_name.to_string(),
+                    })
+            }
+        }
+    }
 
-            self.connections.push(gate);
+    fn shift(&self, signal: u64, factor: u64, left: bool) -> u64 {
+        if factor >= self.width as u64 {
+            return 0;
+        }
+        if left {
+            (signal << factor) & self.mask()
+        } else {
+            signal >> factor
+        }
+    }
 
-            return Process::OR;
+    pub(crate) fn evaluate_gate(&self, gate: &Gate) -> Result<u64, EvaluateErrors> {
+        let signals: Vec<u64> = gate
+            .get_inputs()
+            .into_iter()
+            .enumerate()
+            .map(|(operand, input)| self.resolve_operand(gate, operand, input))
+            .collect::<Result<_, _>>()?;
+
+        let result = match gate {
+            Gate::Relay(_, _) => signals[0],
+            Gate::NOT(_, _) => !signals[0] & self.mask(),
+            Gate::AND(_, _, _) => signals[0] & signals[1],
+            Gate::OR(_, _, _) => signals[0] | signals[1],
+            Gate::LSHIFT(_, _, _) => self.shift(signals[0], signals[1], true),
+            Gate::RSHIFT(_, _, _) => self.shift(signals[0], signals[1], false),
+            Gate::XOR(_, _, _) => signals[0] ^ signals[1],
+            Gate::NAND(_, _, _) => !(signals[0] & signals[1]) & self.mask(),
+            Gate::NOR(_, _, _) => !(signals[0] | signals[1]) & self.mask(),
+            Gate::ADD(_, _, _) => signals[0].wrapping_add(signals[1]) & self.mask(),
+            Gate::SUB(_, _, _) => signals[0].wrapping_sub(signals[1]) & self.mask(),
+            Gate::MUX(_, _, _, _) => {
+                if signals[0] == 0 {
+                    signals[1]
+                } else {
+                    signals[2]
+                }
+            }
         };
 
-        if let Some(caps) = shift_pattern.captures(s) {
-            let input: Input = match caps.name("input") {
-                Some(input) => Input::Wire(input.as_str().to_string()),
-                None => return Process::Error,
-            };
-            let direction: &str = match caps.name("direction") {
-                Some(input) => input.as_str(),
-                None => return Process::Error,
-            };
-            let factor: u16 = match caps.name("factor") {
-                Some(output) => match output.as_str().parse::<u16>() {
-                    Ok(v) => v,
-                    Err(_) => return Process::Error,
-                },
-                None => return Process::Error,
-            };
-            let output: Output = match caps.name("output") {
-                Some(output) => Output::Wire(output.as_str().to_string()),
-                None => return Process::Error,
-            };
+        Ok(result)
+    }
 
-            match direction {
-                "LSHIFT" => {
-                    let gate = Gate::LSHIFT(input, factor, output);
-                    self.connections.push(gate);
+    fn add_gate(&mut self, gate: Gate) {
+        self.source_lines
+            .insert(gate.get_output_wire_name(), self.lines_processed);
+        self.connections.push(gate);
+    }
 
-                    return Process::LSHIFT;
-                }
-                "RSHIFT" => {
-                    let gate = Gate::RSHIFT(input, factor, output);
-                    self.connections.push(gate);
+    pub fn skip_line(&mut self) {
+        self.lines_processed += 1;
+    }
 
-                    return Process::RSHIFT;
-                }
-                _ => return Process::Error,
+    pub fn try_process_instruction(&mut self, s: &str) -> Result<Process, ParseError> {
+        self.lines_processed += 1;
+        let line = self.lines_processed;
+
+        let (instruction, column) = parse_instruction(s, line, self.mask())?;
+
+        let output_wire = match &instruction {
+            Instruction::Signal(_, wire_name) => wire_name.to_string(),
+            Instruction::Gate(gate) => gate.get_output_wire_name(),
+        };
+        if let Some(&first_line) = self.source_lines.get(&output_wire) {
+            return Err(ParseError::MultipleDrivers {
+                line,
+                column,
+                wire: output_wire,
+                first_line,
+            });
+        }
+
+        let process = match instruction {
+            Instruction::Signal(signal, wire_name) => {
+                self.source_lines.insert(wire_name.to_string(), line);
+                self.wire.insert(wire_name, Wire::Signal(signal));
+                Process::Input
+            }
+            Instruction::Gate(gate) => {
+                let process = match gate {
+                    Gate::Relay(_, _) => Process::Relay,
+                    Gate::NOT(_, _) => Process::NOT,
+                    Gate::AND(_, _, _) => Process::AND,
+                    Gate::OR(_, _, _) => Process::OR,
+                    Gate::LSHIFT(_, _, _) => Process::LSHIFT,
+                    Gate::RSHIFT(_, _, _) => Process::RSHIFT,
+                    Gate::XOR(_, _, _) => Process::XOR,
+                    Gate::NAND(_, _, _) => Process::NAND,
+                    Gate::NOR(_, _, _) => Process::NOR,
+                    Gate::ADD(_, _, _) => Process::ADD,
+                    Gate::SUB(_, _, _) => Process::SUB,
+                    Gate::MUX(_, _, _, _) => Process::MUX,
+                };
+                self.add_gate(gate);
+                process
             }
         };
 
-        Process::Error
+        Ok(process)
+    }
+
+    pub fn process_instruction(&mut self, s: &str) -> Process {
+        self.try_process_instruction(s).unwrap_or(Process::Error)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::parse_circuit;
+    use crate::test_circuits::circuit_strategy;
+    use proptest::prelude::*;
 
     #[test]
     fn test_process_instruction() {
@@ -445,4 +765,409 @@ mod tests {
         let wire_y = kit.wire.get("y").expect("Should get wire y");
         assert!(matches!(wire_y, Wire::Signal(456)));
     }
+
+    #[test]
+    fn test_evaluate_circuit_out_of_order() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("d OR e -> f");
+        kit.process_instruction("x AND y -> d");
+        kit.process_instruction("e -> g");
+        kit.process_instruction("NOT x -> e");
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("456 -> y");
+
+        assert!(matches!(kit.evaluate_circuit(), Ok(())));
+        assert!(matches!(kit.wire.get("f"), Some(Wire::Signal(65484))));
+        assert!(matches!(kit.wire.get("g"), Some(Wire::Signal(65412))));
+    }
+
+    #[test]
+    fn test_evaluate_circuit_undriven_wires() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("x AND y -> d");
+        kit.process_instruction("z OR d -> e");
+
+        match kit.evaluate_circuit() {
+            Err(EvaluateErrors::UndrivenWires(wires)) => assert_eq!(wires, vec!["y", "z"]),
+            other => panic!("Should return UndrivenWires, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_circuit_combinational_loop() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("x AND c -> a");
+        kit.process_instruction("a -> b");
+        kit.process_instruction("NOT b -> c");
+        kit.process_instruction("c OR x -> d");
+
+        match kit.evaluate_circuit() {
+            Err(EvaluateErrors::CombinationalLoop(wires)) => {
+                assert_eq!(wires, vec!["a", "b", "c"])
+            }
+            other => panic!("Should return CombinationalLoop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("456 -> y");
+        kit.process_instruction("x AND y -> d");
+        kit.process_instruction("x OR y -> e");
+        kit.process_instruction("d LSHIFT 2 -> f");
+        kit.process_instruction("NOT z -> g");
+
+        assert!(matches!(kit.probe("f"), Ok(288)));
+        assert!(matches!(kit.wire.get("d"), Some(Wire::Signal(72))));
+        assert!(
+            !kit.wire.contains_key("e"),
+            "Wire e is outside the fan-in of f and should not be evaluated"
+        );
+
+        assert!(matches!(kit.probe("x"), Ok(123)));
+        assert!(matches!(
+            kit.probe("g"),
+            Err(EvaluateErrors::UndrivenWires(_))
+        ));
+    }
+
+    #[test]
+    fn test_probe_combinational_loop() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("x AND c -> a");
+        kit.process_instruction("a -> b");
+        kit.process_instruction("NOT b -> c");
+        kit.process_instruction("c OR x -> d");
+
+        match kit.probe("d") {
+            Err(EvaluateErrors::CombinationalLoop(wires)) => {
+                assert_eq!(wires, vec!["a", "b", "c"])
+            }
+            other => panic!("Should return CombinationalLoop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_override_wire() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("456 -> y");
+        kit.process_instruction("x AND y -> d");
+        kit.process_instruction("d OR y -> e");
+        kit.process_instruction("NOT y -> i");
+
+        assert!(matches!(kit.probe("e"), Ok(456)));
+        assert!(matches!(kit.probe("i"), Ok(65079)));
+
+        kit.override_wire("x", 1);
+
+        assert!(matches!(kit.wire.get("d"), Some(Wire::Unknown)));
+        assert!(matches!(kit.wire.get("e"), Some(Wire::Unknown)));
+        assert!(
+            matches!(kit.wire.get("i"), Some(Wire::Signal(65079))),
+            "Wire i does not depend on x and should keep its signal"
+        );
+
+        assert!(matches!(kit.probe("d"), Ok(0)));
+        assert!(matches!(kit.probe("e"), Ok(456)));
+    }
+
+    #[test]
+    fn test_literal_and_wire_operands() {
+        let mut kit = WiresKit::new();
+
+        assert!(matches!(
+            kit.process_instruction("123 -> x"),
+            Process::Input
+        ));
+        assert!(matches!(kit.process_instruction("3 -> n"), Process::Input));
+        assert!(matches!(
+            kit.process_instruction("x OR 1024 -> a"),
+            Process::OR
+        ));
+        assert!(matches!(
+            kit.process_instruction("1 AND 3 -> b"),
+            Process::AND
+        ));
+        assert!(matches!(
+            kit.process_instruction("NOT 0 -> c"),
+            Process::NOT
+        ));
+        assert!(matches!(
+            kit.process_instruction("x LSHIFT n -> d"),
+            Process::LSHIFT
+        ));
+        assert!(matches!(
+            kit.process_instruction("256 RSHIFT n -> e"),
+            Process::RSHIFT
+        ));
+        assert!(matches!(
+            kit.process_instruction("x LSHIFT 16 -> f"),
+            Process::LSHIFT
+        ));
+        assert!(matches!(
+            kit.process_instruction("70000 -> y"),
+            Process::Error
+        ));
+
+        assert!(matches!(kit.evaluate_circuit(), Ok(())));
+        assert!(matches!(kit.wire.get("a"), Some(Wire::Signal(1147))));
+        assert!(matches!(kit.wire.get("b"), Some(Wire::Signal(1))));
+        assert!(matches!(kit.wire.get("c"), Some(Wire::Signal(65535))));
+        assert!(matches!(kit.wire.get("d"), Some(Wire::Signal(984))));
+        assert!(matches!(kit.wire.get("e"), Some(Wire::Signal(32))));
+        assert!(matches!(kit.wire.get("f"), Some(Wire::Signal(0))));
+    }
+
+    #[test]
+    fn test_unresolved_operand() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("x AND y -> d");
+
+        match kit.evaluate_gate(&kit.connections[0]) {
+            Err(EvaluateErrors::UnresolvedOperand {
+                line,
+                operand,
+                wire,
+            }) => {
+                assert_eq!(line, Some(2));
+                assert_eq!(operand, 1);
+                assert_eq!(wire, "y");
+            }
+            other => panic!("Should return UnresolvedOperand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_circuit_widths() {
+        // (width, x, y, d, e, f, g, h, i)
+        let cases: [(u32, [u64; 8]); 4] = [
+            (8, [123, 200, 72, 251, 236, 50, 132, 55]),
+            (16, [123, 456, 72, 507, 492, 114, 65412, 65079]),
+            (32, [123, 456, 72, 507, 492, 114, 4294967172, 4294966839]),
+            (
+                64,
+                [
+                    123,
+                    456,
+                    72,
+                    507,
+                    492,
+                    114,
+                    18446744073709551492,
+                    18446744073709551159,
+                ],
+            ),
+        ];
+
+        for (width, [x, y, d, e, f, g, h, i]) in cases {
+            let mut kit = WiresKit::with_width(width);
+
+            kit.process_instruction(&format!("{} -> x", x));
+            kit.process_instruction(&format!("{} -> y", y));
+            kit.process_instruction("x AND y -> d");
+            kit.process_instruction("x OR y -> e");
+            kit.process_instruction("x LSHIFT 2 -> f");
+            kit.process_instruction("y RSHIFT 2 -> g");
+            kit.process_instruction("NOT x -> h");
+            kit.process_instruction("NOT y -> i");
+
+            assert!(
+                matches!(kit.evaluate_circuit(), Ok(())),
+                "Evaluation should succed at width {}",
+                width
+            );
+
+            for (wire_name, expected) in [
+                ("d", d),
+                ("e", e),
+                ("f", f),
+                ("g", g),
+                ("h", h),
+                ("i", i),
+                ("x", x),
+                ("y", y),
+            ] {
+                let wire = kit.wire.get(wire_name).expect("Should get wire");
+                assert!(
+                    matches!(wire, Wire::Signal(s) if *s == expected),
+                    "Wire {} should be {} at width {}, got {:?}",
+                    wire_name,
+                    expected,
+                    width,
+                    wire
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_width_masking() {
+        let mut kit = WiresKit::with_width(8);
+
+        assert!(matches!(
+            kit.process_instruction("255 -> x"),
+            Process::Input
+        ));
+        assert!(matches!(
+            kit.process_instruction("256 -> y"),
+            Process::Error
+        ));
+        assert!(matches!(
+            kit.process_instruction("x LSHIFT 4 -> a"),
+            Process::LSHIFT
+        ));
+        assert!(matches!(
+            kit.process_instruction("x LSHIFT 8 -> b"),
+            Process::LSHIFT
+        ));
+        assert!(matches!(
+            kit.process_instruction("x RSHIFT 7 -> c"),
+            Process::RSHIFT
+        ));
+
+        assert!(matches!(kit.probe("a"), Ok(240)));
+        assert!(matches!(kit.probe("b"), Ok(0)));
+        assert!(matches!(kit.probe("c"), Ok(1)));
+
+        kit.override_wire("x", 0x1ff);
+        assert!(matches!(kit.wire.get("x"), Some(Wire::Signal(255))));
+
+        let kit = WiresKit::with_width(64);
+        assert_eq!(kit.mask(), u64::MAX);
+        assert_eq!(WiresKit::new().mask(), 0xffff);
+    }
+
+    #[test]
+    fn test_extended_gates() {
+        let mut kit = WiresKit::new();
+
+        kit.process_instruction("123 -> x");
+        kit.process_instruction("456 -> y");
+        kit.process_instruction("0 -> s");
+        assert!(matches!(
+            kit.process_instruction("x XOR y -> a"),
+            Process::XOR
+        ));
+        assert!(matches!(
+            kit.process_instruction("x NAND y -> b"),
+            Process::NAND
+        ));
+        assert!(matches!(
+            kit.process_instruction("x NOR y -> c"),
+            Process::NOR
+        ));
+        assert!(matches!(
+            kit.process_instruction("x ADD y -> d"),
+            Process::ADD
+        ));
+        assert!(matches!(
+            kit.process_instruction("x SUB y -> e"),
+            Process::SUB
+        ));
+        assert!(matches!(
+            kit.process_instruction("65535 ADD 2 -> f"),
+            Process::ADD
+        ));
+        assert!(matches!(
+            kit.process_instruction("s MUX x y -> g"),
+            Process::MUX
+        ));
+        assert!(matches!(
+            kit.process_instruction("1 MUX x y -> h"),
+            Process::MUX
+        ));
+
+        assert!(matches!(kit.evaluate_circuit(), Ok(())));
+        assert!(matches!(kit.wire.get("a"), Some(Wire::Signal(435))));
+        assert!(matches!(kit.wire.get("b"), Some(Wire::Signal(65463))));
+        assert!(matches!(kit.wire.get("c"), Some(Wire::Signal(65028))));
+        assert!(matches!(kit.wire.get("d"), Some(Wire::Signal(579))));
+        assert!(matches!(kit.wire.get("e"), Some(Wire::Signal(65203))));
+        assert!(matches!(kit.wire.get("f"), Some(Wire::Signal(1))));
+        assert!(matches!(kit.wire.get("g"), Some(Wire::Signal(123))));
+        assert!(matches!(kit.wire.get("h"), Some(Wire::Signal(456))));
+    }
+
+    #[test]
+    fn test_full_adder() {
+        let mut kit = WiresKit::with_width(1);
+
+        kit.process_instruction("x00 XOR y00 -> t00");
+        kit.process_instruction("t00 XOR c00 -> z00");
+        kit.process_instruction("x00 AND y00 -> u00");
+        kit.process_instruction("t00 AND c00 -> v00");
+        kit.process_instruction("u00 OR v00 -> z01");
+
+        for bits in 0..8_u64 {
+            let (x, y, c) = (bits & 1, (bits >> 1) & 1, bits >> 2);
+            kit.override_wire("x00", x);
+            kit.override_wire("y00", y);
+            kit.override_wire("c00", c);
+
+            let sum = x + y + c;
+            assert!(matches!(kit.probe("z00"), Ok(s) if s == sum & 1));
+            assert!(matches!(kit.probe("z01"), Ok(s) if s == sum >> 1));
+        }
+    }
+
+    #[test]
+    fn test_to_instructions() {
+        let mut kit = parse_circuit(
+            "x AND y -> d\n456 -> y\n123 -> x\nNOT x -> h\ns MUX d 7 -> m\n1 LSHIFT x -> f\nd -> r",
+        )
+        .expect("Should parse circuit");
+        kit.override_wire("s", 1);
+        kit.probe("m").expect("Should probe wire m");
+
+        assert_eq!(
+            kit.to_instructions(),
+            "1 -> s\n123 -> x\n456 -> y\nx AND y -> d\nNOT x -> h\ns MUX d 7 -> m\n1 LSHIFT x -> f\nd -> r\n"
+        );
+        assert_eq!(kit.to_string(), kit.to_instructions());
+    }
+
+    #[test]
+    fn test_literal_relay_reads_back_as_input() {
+        let mut kit = parse_circuit("x AND y -> d\n").expect("Should parse circuit");
+        kit.connections
+            .push(Gate::Relay(Input::Signal(5), Output::Wire("w".to_string())));
+
+        assert_eq!(kit.to_instructions(), "x AND y -> d\n5 -> w\n");
+
+        let mut reparsed =
+            parse_circuit(&kit.to_instructions()).expect("Should parse printed circuit");
+        assert_eq!(reparsed.connections.len(), 1);
+        assert_eq!(reparsed.wire.get("w"), Some(&Wire::Signal(5)));
+        assert!(matches!(kit.probe("w"), Ok(5)));
+        assert!(matches!(reparsed.probe("w"), Ok(5)));
+    }
+
+    proptest! {
+        // Exact for everything the parser can produce, see `Display for Gate`
+        // for the one gate it can't.
+        #[test]
+        fn test_instructions_round_trip(instructions in circuit_strategy()) {
+            let kit = parse_circuit(&instructions).expect("Should parse generated circuit");
+            let printed = kit.to_instructions();
+            let reparsed = parse_circuit(&printed).expect("Should parse printed circuit");
+
+            prop_assert_eq!(&kit.wire, &reparsed.wire);
+            prop_assert_eq!(&kit.connections, &reparsed.connections);
+            prop_assert_eq!(printed, reparsed.to_instructions());
+        }
+    }
 }
@@ -0,0 +1,428 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::vector2::Vector2;
+
+const NEIGHBOURS_4: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const NEIGHBOURS_8: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum GridParseError<E> {
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidCell {
+        line: usize,
+        column: usize,
+        error: E,
+    },
+}
+
+// Cells stored row by row, addressed with `Vector2` where `x` is the column
+// and `y` the row, starting from the top left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Self {
+            cells: vec![fill; width * height],
+            width,
+            height,
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    // Returns `None` when the number of cells does not match the size.
+    pub fn from_cells(width: usize, height: usize, cells: Vec<T>) -> Option<Self> {
+        if cells.len() != width * height {
+            return None;
+        }
+
+        Some(Self {
+            cells,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn cells_mut(&mut self) -> &mut [T] {
+        &mut self.cells
+    }
+
+    pub fn contains(&self, pos: &Vector2) -> bool {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < self.width && (pos.y as usize) < self.height
+    }
+
+    pub fn index_of(&self, pos: &Vector2) -> Option<usize> {
+        if !self.contains(pos) {
+            return None;
+        }
+
+        Some(pos.y as usize * self.width + pos.x as usize)
+    }
+
+    pub fn position_of(&self, index: usize) -> Vector2 {
+        Vector2::new((index % self.width) as i32, (index / self.width) as i32)
+    }
+
+    pub fn get(&self, pos: &Vector2) -> Option<&T> {
+        self.index_of(pos).map(|index| &self.cells[index])
+    }
+
+    pub fn get_mut(&mut self, pos: &Vector2) -> Option<&mut T> {
+        self.index_of(pos).map(|index| &mut self.cells[index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2, &T)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| (self.position_of(index), cell))
+    }
+
+    fn neighbours<'a>(
+        &'a self,
+        pos: &Vector2,
+        offsets: &'a [(i32, i32)],
+    ) -> impl Iterator<Item = (Vector2, &'a T)> {
+        let (x, y) = (pos.x, pos.y);
+
+        offsets.iter().filter_map(move |(dx, dy)| {
+            let neighbour = Vector2::new(x + dx, y + dy);
+            self.get(&neighbour).map(|cell| (neighbour, cell))
+        })
+    }
+
+    // Neighbours sharing an edge, clockwise from the one above.
+    pub fn neighbours_4(&self, pos: &Vector2) -> impl Iterator<Item = (Vector2, &T)> {
+        self.neighbours(pos, &NEIGHBOURS_4)
+    }
+
+    // Neighbours sharing an edge or a corner, clockwise from the top left.
+    pub fn neighbours_8(&self, pos: &Vector2) -> impl Iterator<Item = (Vector2, &T)> {
+        self.neighbours(pos, &NEIGHBOURS_8)
+    }
+
+    // Cells from `start` stepping by `step` until the edge of the grid.
+    pub fn ray(&self, start: &Vector2, step: &Vector2) -> impl Iterator<Item = &T> {
+        let (dx, dy) = (step.x, step.y);
+        let mut pos = start.clone();
+
+        std::iter::from_fn(move || {
+            let cell = self.get(&pos)?;
+            if dx == 0 && dy == 0 {
+                return None;
+            }
+            pos = Vector2::new(pos.x + dx, pos.y + dy);
+            Some(cell)
+        })
+    }
+
+    pub fn row(&self, y: usize) -> impl Iterator<Item = &T> {
+        self.ray(&Vector2::new(0, y as i32), &Vector2::right())
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        self.ray(&Vector2::new(x as i32, 0), &Vector2::down())
+    }
+
+    // Cells going down and to the right from `start`.
+    pub fn diagonal(&self, start: &Vector2) -> impl Iterator<Item = &T> {
+        self.ray(start, &Vector2::new(1, 1))
+    }
+
+    // Cells going down and to the left from `start`.
+    pub fn anti_diagonal(&self, start: &Vector2) -> impl Iterator<Item = &T> {
+        self.ray(start, &Vector2::new(-1, 1))
+    }
+}
+
+impl<T: FromStr> Grid<T> {
+    fn from_rows<'a, I>(rows: I) -> Result<Self, GridParseError<T::Err>>
+    where
+        I: Iterator<Item = Vec<&'a str>>,
+    {
+        let mut cells: Vec<T> = Vec::new();
+        let mut width: Option<usize> = None;
+        let mut height = 0;
+
+        for (line, row) in rows.enumerate() {
+            let expected = *width.get_or_insert(row.len());
+            if row.len() != expected {
+                return Err(GridParseError::RaggedRow {
+                    line: line + 1,
+                    expected,
+                    found: row.len(),
+                });
+            }
+
+            for (column, cell) in row.into_iter().enumerate() {
+                match cell.parse() {
+                    Ok(cell) => cells.push(cell),
+                    Err(error) => {
+                        return Err(GridParseError::InvalidCell {
+                            line: line + 1,
+                            column: column + 1,
+                            error,
+                        })
+                    }
+                }
+            }
+            height += 1;
+        }
+
+        Ok(Self {
+            cells,
+            width: width.unwrap_or(0),
+            height,
+        })
+    }
+
+    // Parses every character of every non-empty line as one cell.
+    pub fn parse(text: &str) -> Result<Self, GridParseError<T::Err>> {
+        Self::from_rows(text.lines().filter(|line| !line.is_empty()).map(|line| {
+            line.char_indices()
+                .map(|(index, c)| &line[index..index + c.len_utf8()])
+                .collect()
+        }))
+    }
+
+    // Parses whitespace separated words of every non-empty line as cells,
+    // for cells longer than one character such as numbers.
+    pub fn parse_words(text: &str) -> Result<Self, GridParseError<T::Err>> {
+        Self::from_rows(
+            text.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.split_whitespace().collect()),
+        )
+    }
+}
+
+impl<T: FromStr> FromStr for Grid<T> {
+    type Err = GridParseError<T::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Grid::parse(s)
+    }
+}
+
+// Prints one line per row. Cells wider than a character are right aligned
+// and separated by a space so columns line up.
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cells: Vec<String> = self.cells.iter().map(|cell| cell.to_string()).collect();
+        let cell_width = cells
+            .iter()
+            .map(|cell| cell.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for row in cells.chunks(self.width.max(1)) {
+            let line: Vec<String> = row
+                .iter()
+                .map(|cell| format!("{:>width$}", cell, width = cell_width))
+                .collect();
+            let separator = if cell_width > 1 { " " } else { "" };
+            writeln!(f, "{}", line.join(separator))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_get() {
+        let mut grid = Grid::new(3, 2, 0);
+
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert!(matches!(grid.get(&Vector2::new(2, 1)), Some(0)));
+        assert!(grid.get(&Vector2::new(3, 0)).is_none());
+        assert!(grid.get(&Vector2::new(0, -1)).is_none());
+
+        *grid.get_mut(&Vector2::new(1, 1)).unwrap() = 5;
+        assert_eq!(grid.cells(), &[0, 0, 0, 0, 5, 0]);
+        assert_eq!(grid.index_of(&Vector2::new(1, 1)), Some(4));
+        assert_eq!(grid.position_of(4), Vector2::new(1, 1));
+        assert!(grid.get_mut(&Vector2::new(-1, 0)).is_none());
+
+        assert!(Grid::from_cells(2, 2, vec![1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn test_grid_neighbours() {
+        let grid: Grid<u32> = Grid::parse("123\n456\n789").unwrap();
+
+        let corner: Vec<u32> = grid
+            .neighbours_4(&Vector2::new(0, 0))
+            .map(|(_, cell)| *cell)
+            .collect();
+        assert_eq!(corner, vec![2, 4]);
+
+        let centre: Vec<u32> = grid
+            .neighbours_8(&Vector2::new(1, 1))
+            .map(|(_, cell)| *cell)
+            .collect();
+        assert_eq!(centre, vec![1, 2, 3, 6, 9, 8, 7, 4]);
+
+        let edge: Vec<Vector2> = grid
+            .neighbours_8(&Vector2::new(2, 1))
+            .map(|(pos, _)| pos)
+            .collect();
+        assert_eq!(edge.len(), 5);
+        assert!(edge.contains(&Vector2::new(1, 2)));
+    }
+
+    #[test]
+    fn test_grid_lines() {
+        let grid: Grid<char> = "abc\ndef\nghi\n".parse().unwrap();
+
+        assert_eq!(grid.row(1).collect::<String>(), "def");
+        assert_eq!(grid.column(2).collect::<String>(), "cfi");
+        assert_eq!(
+            grid.diagonal(&Vector2::new(0, 0)).collect::<String>(),
+            "aei"
+        );
+        assert_eq!(grid.diagonal(&Vector2::new(1, 0)).collect::<String>(), "bf");
+        assert_eq!(
+            grid.anti_diagonal(&Vector2::new(2, 0)).collect::<String>(),
+            "ceg"
+        );
+        assert_eq!(
+            grid.ray(&Vector2::new(2, 2), &Vector2::up())
+                .collect::<String>(),
+            "ifc"
+        );
+        assert_eq!(grid.row(3).count(), 0);
+        assert_eq!(
+            grid.ray(&Vector2::new(0, 0), &Vector2::new(0, 0)).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_grid_parse() {
+        let grid: Grid<u32> = Grid::parse_words(" 1 20\n300 4\n").unwrap();
+        assert_eq!(grid.cells(), &[1, 20, 300, 4]);
+
+        assert_eq!(
+            Grid::<char>::parse("ab\nc\n"),
+            Err(GridParseError::RaggedRow {
+                line: 2,
+                expected: 2,
+                found: 1,
+            })
+        );
+        assert!(matches!(
+            Grid::<u8>::parse("12\n3x\n"),
+            Err(GridParseError::InvalidCell {
+                line: 2,
+                column: 2,
+                ..
+            })
+        ));
+
+        let empty: Grid<char> = Grid::parse("").unwrap();
+        assert_eq!((empty.width(), empty.height()), (0, 0));
+        assert_eq!(empty.to_string(), "");
+    }
+
+    #[test]
+    fn test_grid_display() {
+        let grid: Grid<char> = Grid::parse("#.\n.#").unwrap();
+        assert_eq!(grid.to_string(), "#.\n.#\n");
+
+        let grid: Grid<u32> = Grid::parse_words("1 20\n300 4").unwrap();
+        assert_eq!(grid.to_string(), "  1  20\n300   4\n");
+    }
+
+    // Day 6 of 2015 rebuilt on top of the grid, to make sure it covers what
+    // `Lights` used to do with its own index helpers.
+    enum Operation {
+        TurnOn,
+        TurnOff,
+        Toggle,
+    }
+
+    fn exec(lights: &mut Grid<u32>, operation: Operation, start: Vector2, end: Vector2) {
+        for y in start.y.min(end.y)..=start.y.max(end.y) {
+            for x in start.x.min(end.x)..=start.x.max(end.x) {
+                if let Some(light) = lights.get_mut(&Vector2::new(x, y)) {
+                    *light = match operation {
+                        Operation::TurnOn => 1,
+                        Operation::TurnOff => 0,
+                        Operation::Toggle => 1 - *light,
+                    };
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_lights() {
+        let mut lights = Grid::new(1000, 1000, 0_u32);
+
+        exec(
+            &mut lights,
+            Operation::TurnOn,
+            Vector2::new(0, 0),
+            Vector2::new(999, 999),
+        );
+        assert_eq!(lights.cells().iter().sum::<u32>(), 1_000_000);
+
+        exec(
+            &mut lights,
+            Operation::Toggle,
+            Vector2::new(999, 0),
+            Vector2::new(0, 0),
+        );
+        assert!(matches!(lights.get(&Vector2::new(500, 0)), Some(0)));
+        assert!(matches!(lights.get(&Vector2::new(500, 1)), Some(1)));
+
+        exec(
+            &mut lights,
+            Operation::TurnOff,
+            Vector2::new(499, 499),
+            Vector2::new(500, 500),
+        );
+        assert_eq!(lights.cells().iter().sum::<u32>(), 1_000_000 - 1000 - 4);
+        assert_eq!(lights.column(500).filter(|&&light| light == 0).count(), 3);
+        assert!(lights
+            .neighbours_8(&Vector2::new(1001, 1001))
+            .next()
+            .is_none());
+    }
+}
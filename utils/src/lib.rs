@@ -1 +1,3 @@
+pub mod grid;
+pub mod rect;
 pub mod vector2;
@@ -0,0 +1,223 @@
+use crate::vector2::Vector2;
+
+// Rectangle including both corners, so `Rect::new(a, a)` covers one cell.
+// `new` normalises the corners so `start` is always the top left one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub start: Vector2,
+    pub end: Vector2,
+}
+
+impl Rect {
+    pub fn new(a: Vector2, b: Vector2) -> Self {
+        Self {
+            start: Vector2::new(a.x.min(b.x), a.y.min(b.y)),
+            end: Vector2::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    pub fn width(&self) -> u64 {
+        (self.end.x as i64 - self.start.x as i64 + 1) as u64
+    }
+
+    pub fn height(&self) -> u64 {
+        (self.end.y as i64 - self.start.y as i64 + 1) as u64
+    }
+
+    // A rect over the whole i32 plane has 2^64 cells, one more than fits in
+    // a u64.
+    pub fn area(&self) -> u128 {
+        self.width() as u128 * self.height() as u128
+    }
+
+    pub fn contains(&self, pos: &Vector2) -> bool {
+        (self.start.x..=self.end.x).contains(&pos.x) && (self.start.y..=self.end.y).contains(&pos.y)
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let start = Vector2::new(
+            self.start.x.max(other.start.x),
+            self.start.y.max(other.start.y),
+        );
+        let end = Vector2::new(self.end.x.min(other.end.x), self.end.y.min(other.end.y));
+
+        if start.x > end.x || start.y > end.y {
+            return None;
+        }
+
+        Some(Rect { start, end })
+    }
+
+    // Smallest rectangle covering both, including any gap between them.
+    pub fn union_bbox(&self, other: &Rect) -> Rect {
+        Rect {
+            start: Vector2::new(
+                self.start.x.min(other.start.x),
+                self.start.y.min(other.start.y),
+            ),
+            end: Vector2::new(self.end.x.max(other.end.x), self.end.y.max(other.end.y)),
+        }
+    }
+
+    // Cells row by row, from the top left corner.
+    pub fn cells(&self) -> impl Iterator<Item = Vector2> {
+        let (x_start, x_end) = (self.start.x, self.end.x);
+
+        (self.start.y..=self.end.y)
+            .flat_map(move |y| (x_start..=x_end).map(move |x| Vector2::new(x, y)))
+    }
+
+    // Splits what is left of `self` after removing `other` into at most four
+    // disjoint rectangles: full width bands above and below the overlap, and
+    // the pieces left and right of it.
+    pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let Some(overlap) = self.intersection(other) else {
+            return vec![self.clone()];
+        };
+
+        let mut pieces: Vec<Rect> = Vec::new();
+
+        if overlap.start.y > self.start.y {
+            pieces.push(Rect {
+                start: self.start.clone(),
+                end: Vector2::new(self.end.x, overlap.start.y - 1),
+            });
+        }
+        if overlap.end.y < self.end.y {
+            pieces.push(Rect {
+                start: Vector2::new(self.start.x, overlap.end.y + 1),
+                end: self.end.clone(),
+            });
+        }
+        if overlap.start.x > self.start.x {
+            pieces.push(Rect {
+                start: Vector2::new(self.start.x, overlap.start.y),
+                end: Vector2::new(overlap.start.x - 1, overlap.end.y),
+            });
+        }
+        if overlap.end.x < self.end.x {
+            pieces.push(Rect {
+                start: Vector2::new(overlap.end.x + 1, overlap.start.y),
+                end: Vector2::new(self.end.x, overlap.end.y),
+            });
+        }
+
+        pieces
+    }
+
+    // Number of cells covered by at least one of the rectangles. Each
+    // rectangle only adds the parts not already covered by earlier ones.
+    pub fn union_area(rects: &[Rect]) -> u128 {
+        let mut covered: Vec<Rect> = Vec::new();
+
+        for rect in rects.iter() {
+            let mut pieces = vec![rect.clone()];
+            for done in covered.iter() {
+                pieces = pieces
+                    .iter()
+                    .flat_map(|piece| piece.subtract(done))
+                    .collect();
+            }
+            covered.extend(pieces);
+        }
+
+        covered.iter().map(Rect::area).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x1: i32, y1: i32, x2: i32, y2: i32) -> Rect {
+        Rect::new(Vector2::new(x1, y1), Vector2::new(x2, y2))
+    }
+
+    #[test]
+    fn test_rect_creation() {
+        let normalised = rect(5, 1, 2, 7);
+
+        assert!(matches!(
+            normalised,
+            Rect {
+                start: Vector2 { x: 2, y: 1 },
+                end: Vector2 { x: 5, y: 7 }
+            }
+        ));
+        assert_eq!(normalised.width(), 4);
+        assert_eq!(normalised.height(), 7);
+        assert_eq!(normalised.area(), 28);
+        assert_eq!(rect(3, 3, 3, 3).area(), 1);
+        assert_eq!(rect(i32::MIN, 0, i32::MAX, 0).area(), 1 << 32);
+        assert_eq!(rect(i32::MIN, i32::MIN, i32::MAX, i32::MAX).area(), 1 << 64);
+
+        assert!(normalised.contains(&Vector2::new(2, 7)));
+        assert!(normalised.contains(&Vector2::new(5, 1)));
+        assert!(!normalised.contains(&Vector2::new(6, 1)));
+        assert!(!normalised.contains(&Vector2::new(2, 0)));
+    }
+
+    #[test]
+    fn test_rect_intersection_and_union() {
+        let a = rect(0, 0, 4, 4);
+
+        assert_eq!(a.intersection(&rect(2, 3, 9, 9)), Some(rect(2, 3, 4, 4)));
+        assert_eq!(a.intersection(&rect(4, 4, 5, 5)), Some(rect(4, 4, 4, 4)));
+        assert_eq!(a.intersection(&rect(5, 0, 6, 4)), None);
+        assert_eq!(a.union_bbox(&rect(-1, 6, 2, 8)), rect(-1, 0, 4, 8));
+    }
+
+    #[test]
+    fn test_rect_cells() {
+        let cells: Vec<Vector2> = rect(1, 1, 2, 2).cells().collect();
+
+        assert_eq!(
+            cells,
+            vec![
+                Vector2::new(1, 1),
+                Vector2::new(2, 1),
+                Vector2::new(1, 2),
+                Vector2::new(2, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rect_subtract() {
+        let a = rect(0, 0, 4, 4);
+
+        let ring = a.subtract(&rect(1, 1, 3, 3));
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring.iter().map(Rect::area).sum::<u128>(), 25 - 9);
+        for (index, piece) in ring.iter().enumerate() {
+            assert!(!piece.contains(&Vector2::new(2, 2)));
+            for other in ring.iter().skip(index + 1) {
+                assert!(piece.intersection(other).is_none());
+            }
+        }
+
+        assert_eq!(a.subtract(&rect(-1, -1, 9, 9)), vec![]);
+        assert_eq!(a.subtract(&rect(7, 7, 9, 9)), vec![a.clone()]);
+        assert_eq!(a.subtract(&rect(0, 0, 4, 1)), vec![rect(0, 2, 4, 4)]);
+    }
+
+    #[test]
+    fn test_rect_union_area() {
+        assert_eq!(Rect::union_area(&[]), 0);
+        assert_eq!(
+            Rect::union_area(&[rect(0, 0, 4, 4), rect(2, 2, 6, 6), rect(1, 1, 1, 1)]),
+            25 + 25 - 9
+        );
+        assert_eq!(
+            Rect::union_area(&[rect(0, 0, 999, 999), rect(499, 499, 500, 500)]),
+            1_000_000
+        );
+        assert_eq!(
+            Rect::union_area(&[
+                rect(i32::MIN, i32::MIN, i32::MAX, i32::MAX),
+                rect(0, 0, 9, 9),
+            ]),
+            1 << 64
+        );
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Vector2 {
     pub x: i32,
     pub y: i32,
@@ -9,7 +9,7 @@ impl Vector2 {
         Self { x, y }
     }
 
-    pub fn add(&mut self, vector_to_add: Vector2) -> () {
+    pub fn add(&mut self, vector_to_add: Vector2) {
         self.x += vector_to_add.x;
         self.y += vector_to_add.y;
     }
@@ -42,7 +42,6 @@ mod tests {
         assert_eq!(vector.y, 4);
     }
 
-
     #[test]
     fn test_vector_add() {
         let mut vector = Vector2::new(3, 5);